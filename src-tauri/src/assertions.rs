@@ -0,0 +1,449 @@
+use crate::http_client::HttpResponse;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_json_path::JsonPath;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AssertionSource {
+    Status,
+    Header,
+    JsonPath,
+    Body,
+    ResponseTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Operator {
+    Equals,
+    NotEquals,
+    Contains,
+    NotContains,
+    Matches,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+    Exists,
+    NotExists,
+    IsType,
+}
+
+// A check on the response, e.g. `json_path $.data.id is_type number`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Assertion {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub source: AssertionSource,
+    // Header name or JSON path; unused for status, body and response time
+    #[serde(default)]
+    pub property: String,
+    pub operator: Operator,
+    #[serde(default)]
+    pub value: String,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AssertionResult {
+    pub name: String,
+    pub assertion: Assertion,
+    pub passed: bool,
+    pub actual: Option<String>,
+    pub message: Option<String>,
+}
+
+impl Assertion {
+    fn check(&self, response: &HttpResponse) -> Result<Option<Value>, String> {
+        match self.source {
+            AssertionSource::Status => Ok(Some(Value::from(response.status))),
+            AssertionSource::ResponseTime => Ok(Some(Value::from(response.response_time as u64))),
+            AssertionSource::Body => Ok(Some(Value::String(response.body.clone()))),
+            AssertionSource::Header => Ok(response
+                .headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(self.property.trim()))
+                .map(|(_, value)| Value::String(value.clone()))),
+            AssertionSource::JsonPath => {
+                let path = JsonPath::parse(self.property.trim())
+                    .map_err(|e| format!("invalid JSON path '{}': {}", self.property, e))?;
+                let body: Value = serde_json::from_str(&response.body)
+                    .map_err(|_| "response body is not JSON".to_string())?;
+                let mut matches: Vec<Value> =
+                    path.query(&body).all().into_iter().cloned().collect();
+                Ok(match matches.len() {
+                    0 => None,
+                    1 => matches.pop(),
+                    _ => Some(Value::Array(matches)),
+                })
+            }
+        }
+    }
+
+    // Human-readable description, e.g. `header Content-Type contains json`
+    fn describe(&self) -> String {
+        let source = match self.source {
+            AssertionSource::Status => "status".to_string(),
+            AssertionSource::ResponseTime => "response time".to_string(),
+            AssertionSource::Body => "body".to_string(),
+            AssertionSource::Header => format!("header {}", self.property),
+            AssertionSource::JsonPath => self.property.clone(),
+        };
+        let operator = match self.operator {
+            Operator::Equals => "equals",
+            Operator::NotEquals => "does not equal",
+            Operator::Contains => "contains",
+            Operator::NotContains => "does not contain",
+            Operator::Matches => "matches",
+            Operator::LessThan => "<",
+            Operator::LessThanOrEqual => "<=",
+            Operator::GreaterThan => ">",
+            Operator::GreaterThanOrEqual => ">=",
+            Operator::Exists => return format!("{} exists", source),
+            Operator::NotExists => return format!("{} does not exist", source),
+            Operator::IsType => "is a",
+        };
+        format!("{} {} {}", source, operator, self.value)
+    }
+}
+
+// Evaluates the enabled assertions against a response
+pub fn evaluate_assertions(
+    assertions: &[Assertion],
+    response: &HttpResponse,
+) -> Vec<AssertionResult> {
+    assertions
+        .iter()
+        .filter(|a| a.enabled)
+        .map(|assertion| {
+            let (passed, actual, message) = match assertion.check(response) {
+                Ok(actual) => {
+                    let outcome = compare(assertion.operator, actual.as_ref(), &assertion.value);
+                    let actual = actual.as_ref().map(text);
+                    match outcome {
+                        Ok(passed) => (passed, actual, None),
+                        Err(message) => (false, actual, Some(message)),
+                    }
+                }
+                Err(message) => (false, None, Some(message)),
+            };
+            AssertionResult {
+                name: assertion.describe(),
+                assertion: assertion.clone(),
+                passed,
+                actual,
+                message,
+            }
+        })
+        .collect()
+}
+
+// Checks a saved assertion list; paths and patterns holding {{variables}}
+// can only be checked once resolved at send time
+pub fn validate_assertions(json: &str) -> Result<(), String> {
+    let assertions: Vec<Assertion> =
+        serde_json::from_str(json).map_err(|e| format!("Invalid assertions: {}", e))?;
+    for assertion in assertions.iter().filter(|a| a.enabled) {
+        if assertion.source == AssertionSource::JsonPath && !assertion.property.contains("{{") {
+            JsonPath::parse(assertion.property.trim())
+                .map_err(|e| format!("Invalid JSON path '{}': {}", assertion.property, e))?;
+        }
+        if assertion.operator == Operator::Matches && !assertion.value.contains("{{") {
+            Regex::new(assertion.value.trim())
+                .map_err(|e| format!("Invalid regex '{}': {}", assertion.value, e))?;
+        }
+    }
+    Ok(())
+}
+
+fn compare(operator: Operator, actual: Option<&Value>, expected: &str) -> Result<bool, String> {
+    let actual = match (operator, actual) {
+        (Operator::Exists, actual) => return Ok(actual.is_some()),
+        (Operator::NotExists, actual) => return Ok(actual.is_none()),
+        (_, Some(actual)) => actual,
+        (_, None) => return Err("value not found".to_string()),
+    };
+    let expected = expected.trim();
+
+    match operator {
+        Operator::Equals => Ok(equals(actual, expected)),
+        Operator::NotEquals => Ok(!equals(actual, expected)),
+        Operator::Contains => Ok(contains(actual, expected)),
+        Operator::NotContains => Ok(!contains(actual, expected)),
+        Operator::Matches => {
+            let pattern =
+                Regex::new(expected).map_err(|e| format!("invalid regex '{}': {}", expected, e))?;
+            Ok(pattern.is_match(&text(actual)))
+        }
+        Operator::LessThan => numbers(actual, expected).map(|(a, b)| a < b),
+        Operator::LessThanOrEqual => numbers(actual, expected).map(|(a, b)| a <= b),
+        Operator::GreaterThan => numbers(actual, expected).map(|(a, b)| a > b),
+        Operator::GreaterThanOrEqual => numbers(actual, expected).map(|(a, b)| a >= b),
+        Operator::IsType => {
+            let types = ["string", "number", "boolean", "object", "array", "null"];
+            if !types.contains(&expected) {
+                return Err(format!(
+                    "unknown type '{}' (expected one of {})",
+                    expected,
+                    types.join(", ")
+                ));
+            }
+            Ok(type_name(actual) == expected)
+        }
+        Operator::Exists | Operator::NotExists => unreachable!(),
+    }
+}
+
+// Numbers compare numerically, strings by their raw text and anything else
+// as JSON, so `1`, `1.0` and `"1"` match a numeric 1
+fn equals(actual: &Value, expected: &str) -> bool {
+    let actual_text = text(actual);
+    if actual_text == expected {
+        return true;
+    }
+    if let (Ok(a), Ok(b)) = (actual_text.parse::<f64>(), expected.parse::<f64>()) {
+        return a == b;
+    }
+    serde_json::from_str::<Value>(expected).is_ok_and(|e| &e == actual)
+}
+
+fn contains(actual: &Value, expected: &str) -> bool {
+    match actual {
+        Value::Array(items) => items.iter().any(|item| equals(item, expected)),
+        Value::Object(map) => map.contains_key(expected),
+        other => text(other).contains(expected),
+    }
+}
+
+fn numbers(actual: &Value, expected: &str) -> Result<(f64, f64), String> {
+    let a = text(actual)
+        .parse::<f64>()
+        .map_err(|_| format!("value '{}' is not a number", text(actual)))?;
+    let b = expected
+        .parse::<f64>()
+        .map_err(|_| format!("'{}' is not a number", expected))?;
+    Ok((a, b))
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn response(body: &str) -> HttpResponse {
+        serde_json::from_value(json!({
+            "status": 201,
+            "status_text": "Created",
+            "headers": {"content-type": "application/json; charset=utf-8"},
+            "body": body,
+            "response_time": 120,
+            "size": body.len(),
+            "cookies": [],
+        }))
+        .unwrap()
+    }
+
+    fn assertion(source: &str, property: &str, operator: &str, value: &str) -> Assertion {
+        serde_json::from_value(json!({
+            "source": source,
+            "property": property,
+            "operator": operator,
+            "value": value,
+        }))
+        .unwrap()
+    }
+
+    fn evaluate(assertion: Assertion, body: &str) -> AssertionResult {
+        evaluate_assertions(&[assertion], &response(body))
+            .pop()
+            .unwrap()
+    }
+
+    #[test]
+    fn equals_compares_numbers_numerically() {
+        assert!(equals(&json!(1), "1"));
+        assert!(equals(&json!(1), "1.0"));
+        assert!(equals(&json!(1.5), "1.50"));
+        assert!(equals(&json!("1"), "1"));
+        assert!(!equals(&json!(1), "2"));
+    }
+
+    #[test]
+    fn equals_compares_strings_by_text() {
+        assert!(equals(&json!("abc"), "abc"));
+        assert!(!equals(&json!("abc"), "abd"));
+        // The quoted JSON form matches too
+        assert!(equals(&json!("abc"), "\"abc\""));
+        assert!(equals(&json!(true), "true"));
+        assert!(equals(&json!(null), "null"));
+        assert!(equals(&json!({"a": [1, 2]}), r#"{ "a": [1, 2] }"#));
+    }
+
+    #[test]
+    fn ordering_needs_numbers() {
+        assert_eq!(
+            compare(Operator::LessThan, Some(&json!(120)), "200"),
+            Ok(true)
+        );
+        assert_eq!(
+            compare(Operator::GreaterThanOrEqual, Some(&json!("10")), "9"),
+            Ok(true)
+        );
+        // Compared as numbers, not text, so "10" > "9"
+        assert_eq!(
+            compare(Operator::LessThan, Some(&json!("10")), "9"),
+            Ok(false)
+        );
+        assert_eq!(
+            compare(Operator::LessThan, Some(&json!("abc")), "9"),
+            Err("value 'abc' is not a number".to_string())
+        );
+        assert_eq!(
+            compare(Operator::LessThan, Some(&json!(1)), "ten"),
+            Err("'ten' is not a number".to_string())
+        );
+    }
+
+    #[test]
+    fn is_type_checks_json_types() {
+        let cases = [
+            (json!("1"), "string"),
+            (json!(1), "number"),
+            (json!(1.5), "number"),
+            (json!(false), "boolean"),
+            (json!({}), "object"),
+            (json!([]), "array"),
+            (json!(null), "null"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(
+                compare(Operator::IsType, Some(&value), expected),
+                Ok(true),
+                "{} is a {}",
+                value,
+                expected
+            );
+        }
+        assert_eq!(
+            compare(Operator::IsType, Some(&json!("1")), "number"),
+            Ok(false)
+        );
+        assert!(compare(Operator::IsType, Some(&json!(1)), "integer")
+            .unwrap_err()
+            .starts_with("unknown type 'integer'"));
+    }
+
+    #[test]
+    fn contains_by_value_type() {
+        assert!(contains(&json!([1, "two"]), "1"));
+        assert!(contains(&json!([1, "two"]), "two"));
+        assert!(contains(&json!({"id": 1}), "id"));
+        assert!(!contains(&json!({"id": 1}), "1"));
+        assert!(contains(&json!("hello world"), "lo w"));
+    }
+
+    #[test]
+    fn exists_needs_no_value() {
+        assert_eq!(compare(Operator::Exists, None, ""), Ok(false));
+        assert_eq!(compare(Operator::NotExists, None, ""), Ok(true));
+        assert_eq!(
+            compare(Operator::Equals, None, "1"),
+            Err("value not found".to_string())
+        );
+    }
+
+    #[test]
+    fn evaluates_response_sources() {
+        let body = r#"{"data": {"id": 7, "tags": ["a", "b"]}}"#;
+
+        let result = evaluate(assertion("status", "", "equals", "201"), body);
+        assert!(result.passed);
+        assert_eq!(result.name, "status equals 201");
+
+        let result = evaluate(
+            assertion("header", "Content-Type", "contains", "json"),
+            body,
+        );
+        assert!(result.passed);
+        assert_eq!(
+            result.actual.as_deref(),
+            Some("application/json; charset=utf-8")
+        );
+
+        let result = evaluate(
+            assertion("json_path", "$.data.id", "is_type", "number"),
+            body,
+        );
+        assert!(result.passed);
+        assert_eq!(result.actual.as_deref(), Some("7"));
+
+        let result = evaluate(
+            assertion("json_path", "$.data.tags[*]", "contains", "b"),
+            body,
+        );
+        assert!(result.passed, "several matches are checked as an array");
+
+        let result = evaluate(assertion("response_time", "", "less_than", "100"), body);
+        assert!(!result.passed);
+        assert_eq!(result.name, "response time < 100");
+
+        let result = evaluate(assertion("body", "", "matches", r#""id":\s*\d+"#), body);
+        assert!(result.passed);
+    }
+
+    #[test]
+    fn reports_why_assertions_fail() {
+        let result = evaluate(assertion("json_path", "$.id", "exists", ""), "not json");
+        assert!(!result.passed);
+        assert_eq!(result.message.as_deref(), Some("response body is not JSON"));
+
+        let result = evaluate(assertion("json_path", "$.missing", "equals", "1"), "{}");
+        assert_eq!(result.message.as_deref(), Some("value not found"));
+        assert_eq!(result.actual, None);
+    }
+
+    #[test]
+    fn skips_disabled_assertions() {
+        let mut disabled = assertion("status", "", "equals", "500");
+        disabled.enabled = false;
+        assert!(evaluate_assertions(&[disabled], &response("")).is_empty());
+    }
+
+    #[test]
+    fn validates_saved_assertions() {
+        let valid = json!([
+            {"source": "json_path", "property": "$.items[0]", "operator": "exists"},
+            {"source": "json_path", "property": "{{path}}", "operator": "exists"},
+            {"source": "body", "operator": "matches", "value": "^ok"},
+        ]);
+        assert!(validate_assertions(&valid.to_string()).is_ok());
+
+        let invalid = json!([{"source": "body", "operator": "matches", "value": "("}]);
+        assert!(validate_assertions(&invalid.to_string())
+            .unwrap_err()
+            .starts_with("Invalid regex '('"));
+    }
+}
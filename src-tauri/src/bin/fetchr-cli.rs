@@ -0,0 +1,4 @@
+// Headless runner for collections, e.g. in CI
+fn main() {
+    std::process::exit(fetchr_lib::run_cli())
+}
@@ -0,0 +1,825 @@
+use crate::assertions::Assertion;
+use crate::data_file::read_data_file;
+use crate::db::{Collection, Database, Environment, Request};
+use crate::extraction::ExtractionRule;
+use crate::http_client::KeyValue;
+use crate::json_schema::{NamedSchema, ResponseSchema};
+use crate::postman_import::{
+    parse_postman_collection, parse_postman_environment, ImportedCollection, ImportedFolder,
+    ImportedHeader, ImportedRequest, ImportedVariable,
+};
+use crate::report::{render_report, ReportFormat};
+use crate::runner::{
+    check_failures, http_request, run_collection, RunEvent, RunOptions, RunSummary,
+};
+use crate::secrets::{map_variable_secrets, Vault};
+use crate::variables::{migrate_variable_schema, validate_variables};
+use crate::{execute_request, save_imported, AppState};
+use clap::{Args, Parser, Subcommand};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Same directory as the desktop app's data (Tauri's app_data_dir)
+const APP_IDENTIFIER: &str = "dev.cyberlion.fetchr";
+
+#[derive(Parser)]
+#[command(
+    name = "fetchr-cli",
+    version,
+    about = "Run fetchr collections and saved requests from the terminal"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run every request in a collection or folder; exits with 1 when any fails
+    Run(RunArgs),
+    /// Send one saved request and print its response; exits with 1 when a check fails
+    Send(SendArgs),
+}
+
+// Where collections, environments and variables come from
+#[derive(Args)]
+struct Source {
+    /// fetchr database to use instead of the desktop app's
+    #[arg(long, value_name = "PATH", conflicts_with = "file")]
+    db: Option<PathBuf>,
+    /// Exported fetchr collection or Postman collection to run instead of the database
+    #[arg(long, value_name = "PATH")]
+    file: Option<PathBuf>,
+    /// Passphrase for the vault holding encrypted credentials and secret variables
+    #[arg(
+        long,
+        value_name = "PASSPHRASE",
+        env = "FETCHR_VAULT_PASSPHRASE",
+        hide_env_values = true
+    )]
+    vault_passphrase: Option<String>,
+    /// Postman environment export to use with --file; it is the active environment unless --env names others
+    #[arg(
+        long = "environment",
+        value_name = "PATH",
+        requires = "file",
+        conflicts_with = "db"
+    )]
+    environment_file: Option<PathBuf>,
+    /// Environment name or id; repeat to layer several, later ones overriding earlier ones
+    #[arg(short, long = "env", value_name = "NAME")]
+    environments: Vec<String>,
+    /// Variable as KEY=VALUE, overriding every other scope; may be repeated
+    #[arg(long = "var", value_name = "KEY=VALUE", value_parser = parse_override)]
+    overrides: Vec<KeyValue>,
+}
+
+#[derive(Args)]
+struct RunArgs {
+    /// Collection or folder path, e.g. "My API/Users", or its id; defaults to the whole file with --file
+    collection: Option<String>,
+    #[command(flatten)]
+    source: Source,
+    /// CSV or JSON data file; the requests run once per row
+    #[arg(short, long, value_name = "PATH")]
+    data: Option<PathBuf>,
+    /// Write a JUnit XML report to this path
+    #[arg(long, value_name = "PATH")]
+    junit: Option<PathBuf>,
+    /// Write a JSON report to this path
+    #[arg(long, value_name = "PATH")]
+    json: Option<PathBuf>,
+    /// Write an HTML report to this path
+    #[arg(long, value_name = "PATH")]
+    html: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct SendArgs {
+    /// Request path, e.g. "My API/Users/Get user", or its id
+    request: String,
+    #[command(flatten)]
+    source: Source,
+    /// Print the response as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+// The response as printed by `send --json`
+#[derive(Serialize)]
+struct SendOutput<'a> {
+    status: u16,
+    status_text: &'a str,
+    response_time: u128,
+    size: usize,
+    headers: BTreeMap<&'a str, &'a str>,
+    body: &'a str,
+    failures: Vec<String>,
+}
+
+// Runs the command line and returns the process exit code: 0 on success,
+// 1 when a request failed and 2 when the run could not start
+pub fn run() -> i32 {
+    let cli = Cli::parse();
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return 2;
+        }
+    };
+    let result = match cli.command {
+        Command::Run(args) => runtime.block_on(run_command(args)),
+        Command::Send(args) => runtime.block_on(send_command(args)),
+    };
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            2
+        }
+    }
+}
+
+async fn run_command(args: RunArgs) -> Result<i32, String> {
+    let (state, imported_root) = open_source(&args.source)?;
+    let collection_id = {
+        let db = state.db.lock().unwrap();
+        match (&args.collection, imported_root) {
+            (Some(path), _) => find_collection(&db, path)?.id,
+            (None, Some(root_id)) => root_id,
+            (None, None) => return Err("Name a collection or folder to run".to_string()),
+        }
+    };
+
+    let mut options = RunOptions {
+        environment_ids: environment_ids(&state, &args.source.environments)?,
+        overrides: args.source.overrides.clone(),
+        ..RunOptions::default()
+    };
+    if let Some(path) = &args.data {
+        options.iterations = read_data_file(&path.to_string_lossy())?;
+    }
+
+    let summary = run_collection(&state, &collection_id, &options, progress_printer()).await?;
+    print_summary(&summary);
+
+    let reports = [
+        (&args.junit, ReportFormat::Junit),
+        (&args.json, ReportFormat::Json),
+        (&args.html, ReportFormat::Html),
+    ];
+    for (path, format) in reports {
+        if let Some(path) = path {
+            let report = render_report(&summary, format)?;
+            std::fs::write(path, report)
+                .map_err(|e| format!("Cannot write report {}: {}", path.display(), e))?;
+        }
+    }
+
+    Ok(if summary.failed > 0 { 1 } else { 0 })
+}
+
+async fn send_command(args: SendArgs) -> Result<i32, String> {
+    let (state, _) = open_source(&args.source)?;
+    let request = {
+        let db = state.db.lock().unwrap();
+        find_request(&db, &args.request)?
+    };
+
+    let mut http_request = http_request(&request)?;
+    http_request.environment_ids = environment_ids(&state, &args.source.environments)?;
+    http_request
+        .variables
+        .extend(args.source.overrides.iter().cloned());
+    let response = execute_request(&state, http_request).await?;
+
+    for issue in &response.variable_issues {
+        eprintln!("warning: {}", issue);
+    }
+    for log in &response.script_logs {
+        eprintln!("console.{}: {}", log.level, log.message);
+    }
+    let failures = check_failures(
+        &response.assertion_results,
+        &response.test_results,
+        response.schema_validation.as_ref(),
+    );
+    if args.json {
+        let output = SendOutput {
+            status: response.status,
+            status_text: &response.status_text,
+            response_time: response.response_time,
+            size: response.size,
+            headers: response
+                .headers
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect(),
+            body: &response.body,
+            failures: failures.clone(),
+        };
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&output).map_err(|e| e.to_string())?
+        );
+    } else {
+        println!(
+            "{} {}  {} ms  {} bytes",
+            response.status, response.status_text, response.response_time, response.size
+        );
+        let headers: BTreeMap<_, _> = response.headers.iter().collect();
+        for (key, value) in headers {
+            println!("{}: {}", key, value);
+        }
+        println!();
+        print!("{}", response.body);
+        if !response.body.ends_with('\n') {
+            println!();
+        }
+        for failure in &failures {
+            eprintln!("✗ {}", failure);
+        }
+    }
+
+    Ok(if failures.is_empty() { 0 } else { 1 })
+}
+
+// Opens the app's database, another database, or an in-memory one holding
+// the collection file; for files, also returns the imported root's id
+fn open_source(source: &Source) -> Result<(AppState, Option<String>), String> {
+    let (db, file) = match &source.file {
+        Some(file) => (
+            Database::new(":memory:").map_err(|e| e.to_string())?,
+            Some(file),
+        ),
+        None => {
+            let path = match &source.db {
+                Some(path) => path.clone(),
+                None => dirs::data_dir()
+                    .ok_or_else(|| "Cannot find the user data directory; pass --db".to_string())?
+                    .join(APP_IDENTIFIER)
+                    .join("fetchr.db"),
+            };
+            // Don't create an empty database for a mistyped path
+            if !path.exists() {
+                return Err(format!(
+                    "No fetchr database at {}; pass --db or --file",
+                    path.display()
+                ));
+            }
+            let db = Database::new(&path.to_string_lossy()).map_err(|e| e.to_string())?;
+            migrate_variable_schema(&db)?;
+            (db, None)
+        }
+    };
+
+    let mut vault = Vault::load(&db)?;
+    match &source.vault_passphrase {
+        Some(passphrase) => vault.unlock(&db, passphrase)?,
+        None if vault.status().configured => eprintln!(
+            "warning: the vault is locked, so encrypted credentials and secret variables are unavailable; set FETCHR_VAULT_PASSPHRASE to unlock it"
+        ),
+        None => {}
+    }
+
+    if let Some(path) = &source.environment_file {
+        import_environment_file(&db, &vault, path)?;
+    }
+    let imported_root = match file {
+        Some(file) => Some(save_imported(&db, &vault, read_collection_file(file)?)?),
+        None => None,
+    };
+    Ok((
+        AppState {
+            db: Mutex::new(db),
+            vault: Mutex::new(vault),
+        },
+        imported_root,
+    ))
+}
+
+// Finds a collection or folder by id, or by its path of names from the top
+// level, e.g. "My API/Users"
+fn find_collection(db: &Database, path: &str) -> Result<Collection, String> {
+    let collections = db.get_all_collections().map_err(|e| e.to_string())?;
+    if let Some(collection) = collections.iter().find(|c| c.id == path) {
+        return Ok(collection.clone());
+    }
+
+    let mut found: Option<&Collection> = None;
+    for name in path.split('/').map(str::trim).filter(|n| !n.is_empty()) {
+        let parent_id = found.map(|c| c.id.as_str());
+        found = Some(
+            collections
+                .iter()
+                .find(|c| c.parent_id.as_deref() == parent_id && c.name == name)
+                .ok_or_else(|| format!("No collection or folder named '{}'", path))?,
+        );
+    }
+    found
+        .cloned()
+        .ok_or_else(|| format!("No collection or folder named '{}'", path))
+}
+
+// Finds a saved request by id, or by its collection path and name, e.g.
+// "My API/Users/Get user"
+fn find_request(db: &Database, path: &str) -> Result<Request, String> {
+    if let Some(request) = db.get_request(path).map_err(|e| e.to_string())? {
+        return Ok(request);
+    }
+    let not_found = || format!("No saved request named '{}'", path);
+    let (folder, name) = path.trim().rsplit_once('/').ok_or_else(not_found)?;
+    let folder = find_collection(db, folder)?;
+    db.get_requests_by_collection(&folder.id)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|r| r.name == name.trim())
+        .ok_or_else(not_found)
+}
+
+// Looks environments up by name or id; none means the active environment
+fn environment_ids(state: &AppState, names: &[String]) -> Result<Option<Vec<String>>, String> {
+    if names.is_empty() {
+        return Ok(None);
+    }
+    let db = state.db.lock().unwrap();
+    let environments = db.get_all_environments().map_err(|e| e.to_string())?;
+    names
+        .iter()
+        .map(|name| {
+            environments
+                .iter()
+                .find(|e| e.id == *name || e.name == *name)
+                .map(|e| e.id.clone())
+                .ok_or_else(|| format!("No environment named '{}'", name))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+fn parse_override(text: &str) -> Result<KeyValue, String> {
+    let (key, value) = text
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", text))?;
+    if key.trim().is_empty() {
+        return Err(format!("missing variable name in '{}'", text));
+    }
+    Ok(KeyValue {
+        key: key.trim().to_string(),
+        value: value.to_string(),
+        enabled: true,
+    })
+}
+
+// A collection file written by fetchr's Export
+#[derive(Deserialize)]
+struct ExportedCollection {
+    name: String,
+    #[serde(default)]
+    auth_type: String,
+    #[serde(default)]
+    auth_data: Value,
+    #[serde(default)]
+    headers: Vec<ImportedHeader>,
+    #[serde(default)]
+    variables: Vec<ExportedVariable>,
+    #[serde(default)]
+    pre_request_script: String,
+    #[serde(default)]
+    test_script: String,
+    #[serde(default)]
+    schemas: Vec<NamedSchema>,
+    // Subfolders, exported in the same shape
+    #[serde(default)]
+    folders: Vec<ExportedCollection>,
+    #[serde(default)]
+    requests: Vec<ExportedRequest>,
+}
+
+#[derive(Deserialize)]
+struct ExportedVariable {
+    key: String,
+    #[serde(default)]
+    value: String,
+    #[serde(default)]
+    initial_value: String,
+}
+
+#[derive(Deserialize)]
+struct ExportedRequest {
+    name: String,
+    method: String,
+    url: String,
+    #[serde(default)]
+    headers: Vec<ImportedHeader>,
+    #[serde(default)]
+    body: String,
+    #[serde(default)]
+    body_type: String,
+    #[serde(default)]
+    auth_type: String,
+    #[serde(default)]
+    auth_data: Value,
+    #[serde(default)]
+    variables: Vec<KeyValue>,
+    #[serde(default)]
+    pre_request_script: String,
+    #[serde(default)]
+    test_script: String,
+    #[serde(default)]
+    assertions: Vec<Assertion>,
+    #[serde(default)]
+    extractions: Vec<ExtractionRule>,
+    #[serde(default)]
+    response_schema: Option<ResponseSchema>,
+}
+
+// Saves a Postman environment export as the active environment
+fn import_environment_file(db: &Database, vault: &Vault, path: &Path) -> Result<(), String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let imported = parse_postman_environment(&text)?;
+    let variables = serde_json::to_string(&imported.variables).map_err(|e| e.to_string())?;
+    let env = Environment {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: imported.name,
+        variables: map_variable_secrets(&validate_variables(&variables)?, |v| vault.seal(v))?,
+        is_active: true,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        dotenv_path: None,
+        parent_id: None,
+    };
+    db.save_environment(&env).map_err(|e| e.to_string())
+}
+
+// Reads a Postman collection (recognised by its `info` block) or a fetchr export
+fn read_collection_file(path: &Path) -> Result<ImportedCollection, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let json: Value = serde_json::from_str(&text)
+        .map_err(|e| format!("Invalid collection file {}: {}", path.display(), e))?;
+    if json.get("info").is_some() {
+        let imported = parse_postman_collection(&text)?;
+        for warning in &imported.warnings {
+            eprintln!("warning: {}", warning);
+        }
+        return Ok(imported);
+    }
+
+    let exported: ExportedCollection = serde_json::from_value(json)
+        .map_err(|e| format!("Invalid collection file {}: {}", path.display(), e))?;
+    let mut folders = Vec::new();
+    let mut requests = Vec::new();
+    add_exported_items(
+        exported.folders,
+        exported.requests,
+        &[],
+        &mut folders,
+        &mut requests,
+    );
+    Ok(ImportedCollection {
+        name: exported.name,
+        auth_type: exported.auth_type,
+        auth_data: auth_data_json(&exported.auth_data),
+        headers: exported.headers,
+        variables: imported_variables(exported.variables),
+        folders,
+        requests,
+        pre_request_script: exported.pre_request_script,
+        test_script: exported.test_script,
+        schemas: exported.schemas,
+        warnings: Vec::new(),
+    })
+}
+
+// Flattens exported subfolders into folders and requests located by path
+fn add_exported_items(
+    exported_folders: Vec<ExportedCollection>,
+    exported_requests: Vec<ExportedRequest>,
+    path: &[String],
+    folders: &mut Vec<ImportedFolder>,
+    requests: &mut Vec<ImportedRequest>,
+) {
+    requests.extend(exported_requests.into_iter().map(|r| ImportedRequest {
+        name: r.name,
+        method: r.method,
+        url: r.url,
+        headers: r.headers,
+        body: r.body,
+        body_type: r.body_type,
+        auth_type: r.auth_type,
+        auth_data: auth_data_json(&r.auth_data),
+        form_data: Vec::new(),
+        folder_path: path.to_vec(),
+        variables: r.variables,
+        pre_request_script: r.pre_request_script,
+        test_script: r.test_script,
+        assertions: r.assertions,
+        extractions: r.extractions,
+        response_schema: r.response_schema,
+    }));
+    for folder in exported_folders {
+        let mut folder_path = path.to_vec();
+        folder_path.push(folder.name.clone());
+        folders.push(ImportedFolder {
+            name: folder.name,
+            parent_path: path.to_vec(),
+            auth_type: folder.auth_type,
+            auth_data: auth_data_json(&folder.auth_data),
+            headers: folder.headers,
+            variables: imported_variables(folder.variables),
+            pre_request_script: folder.pre_request_script,
+            test_script: folder.test_script,
+            schemas: folder.schemas,
+        });
+        add_exported_items(
+            folder.folders,
+            folder.requests,
+            &folder_path,
+            folders,
+            requests,
+        );
+    }
+}
+
+fn auth_data_json(value: &Value) -> String {
+    if value.is_object() {
+        value.to_string()
+    } else {
+        "{}".to_string()
+    }
+}
+
+fn imported_variables(variables: Vec<ExportedVariable>) -> Vec<ImportedVariable> {
+    variables
+        .into_iter()
+        .map(|v| ImportedVariable {
+            key: v.key,
+            value: if v.value.is_empty() {
+                v.initial_value
+            } else {
+                v.value
+            },
+        })
+        .collect()
+}
+
+// Prints one line per request, with its failures indented below it, and a
+// heading before each iteration when there are several
+fn progress_printer() -> impl FnMut(RunEvent) {
+    let mut iterations = 1;
+    let mut current_iteration = None;
+    move |event| match event {
+        RunEvent::Started {
+            iterations: count,
+            total,
+            ..
+        } => {
+            iterations = count;
+            println!("Running {} request(s)", total);
+        }
+        RunEvent::RequestStarted { iteration, .. } => {
+            if iterations > 1 && current_iteration != Some(iteration) {
+                println!("\nIteration {}/{}", iteration + 1, iterations);
+            }
+            current_iteration = Some(iteration);
+        }
+        RunEvent::RequestFinished { result, .. } => {
+            let name = if result.folder.is_empty() {
+                result.name.clone()
+            } else {
+                format!("{}/{}", result.folder, result.name)
+            };
+            let status = match result.status {
+                Some(status) => format!("{}  {} ms", status, result.response_time),
+                None => "error".to_string(),
+            };
+            let mark = if result.passed { "✓" } else { "✗" };
+            println!("{} {} {}  {}", mark, result.method, name, status);
+            for failure in result.failures() {
+                println!("    {}", failure);
+            }
+        }
+    }
+}
+
+fn print_summary(summary: &RunSummary) {
+    println!(
+        "\n{} passed, {} failed, {} request(s) in {} ms",
+        summary.passed, summary.failed, summary.total, summary.duration
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redact::Redactor;
+    use serde_json::json;
+
+    fn collection(id: &str, parent_id: Option<&str>, extra: Value) -> Collection {
+        let mut fields = json!({
+            "id": id,
+            "name": id,
+            "parent_id": parent_id,
+            "is_folder": true,
+            "created_at": "",
+        });
+        fields
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(fields).unwrap()
+    }
+
+    fn json_field(json: &str) -> Value {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn fetchr_exports_keep_tests_and_subfolders() {
+        let db = Database::new(":memory:").unwrap();
+        let vault = Vault::default();
+        let schemas = json!([{"name": "user", "schema": "{\"type\": \"object\"}"}]);
+        let assertions = json!([{"enabled": true, "source": "status", "property": "", "operator": "equals", "value": "200"}]);
+        let extractions = json!([{"enabled": true, "source": "json_path", "expression": "$.id", "variable": "user_id", "scope": "environment"}]);
+        let response_schema = json!({"source": "collection", "name": "user"});
+        let root = collection(
+            "api",
+            None,
+            json!({"test_script": "pm.test('ok', () => {});", "schemas": schemas.to_string()}),
+        );
+        let folder = collection(
+            "users",
+            Some("api"),
+            json!({"variables": json!([{"key": "page", "value": "2"}]).to_string()}),
+        );
+        db.create_collection(&root).unwrap();
+        db.create_collection(&folder).unwrap();
+        db.create_collection(&collection("admin", Some("users"), json!({})))
+            .unwrap();
+        let request: Request = serde_json::from_value(json!({
+            "id": "r1",
+            "collection_id": "users",
+            "name": "Get user",
+            "method": "GET",
+            "url": "{{host}}/users/1",
+            "headers": "[]",
+            "body": "",
+            "body_type": "none",
+            "auth_type": "inherit",
+            "auth_data": "{}",
+            "created_at": "",
+            "updated_at": "",
+            "pre_request_script": "pm.variables.set('id', 1);",
+            "test_script": "pm.test('is user', () => {});",
+            "assertions": assertions.to_string(),
+            "extractions": extractions.to_string(),
+            "response_schema": response_schema.to_string(),
+        }))
+        .unwrap();
+        db.save_request(&request).unwrap();
+
+        let collections = db.get_all_collections().unwrap();
+        let exported = crate::export_folder(
+            &db,
+            &vault,
+            &Redactor::new(true, Vec::new()),
+            &collections,
+            &root,
+        )
+        .unwrap();
+        let dir = std::env::temp_dir().join(format!("fetchr-export-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("api.json");
+        std::fs::write(&path, exported.to_string()).unwrap();
+
+        let imported = Database::new(":memory:").unwrap();
+        let root_id =
+            save_imported(&imported, &vault, read_collection_file(&path).unwrap()).unwrap();
+        let collections = imported.get_all_collections().unwrap();
+        let child = |parent: &str, name: &str| {
+            collections
+                .iter()
+                .find(|c| c.parent_id.as_deref() == Some(parent) && c.name == name)
+                .cloned()
+                .unwrap()
+        };
+        let root = collections.iter().find(|c| c.id == root_id).unwrap();
+        assert_eq!(root.test_script, "pm.test('ok', () => {});");
+        assert_eq!(json_field(&root.schemas), schemas);
+        let users = child(&root_id, "users");
+        assert_eq!(json_field(&users.variables)[0]["value"], "2");
+        child(&users.id, "admin");
+
+        let requests = imported.get_requests_by_collection(&users.id).unwrap();
+        assert_eq!(requests.len(), 1);
+        let request = &requests[0];
+        assert_eq!(request.pre_request_script, "pm.variables.set('id', 1);");
+        assert_eq!(request.test_script, "pm.test('is user', () => {});");
+        assert_eq!(json_field(&request.assertions), assertions);
+        assert_eq!(json_field(&request.extractions), extractions);
+        assert_eq!(json_field(&request.response_schema), response_schema);
+    }
+
+    fn send_args(args: &[&str]) -> Result<SendArgs, clap::Error> {
+        let cli = Cli::try_parse_from(["fetchr-cli", "send"].iter().chain(args))?;
+        let Command::Send(args) = cli.command else {
+            unreachable!()
+        };
+        Ok(args)
+    }
+
+    #[tokio::test]
+    async fn send_finds_requests_by_path_and_reports_failures() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let host = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                let mut buffer = [0; 4096];
+                let _ = std::io::Read::read(&mut stream, &mut buffer);
+                let _ = std::io::Write::write_all(
+                    &mut stream,
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}",
+                );
+            }
+        });
+
+        let dir = std::env::temp_dir().join(format!("fetchr-send-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("fetchr.db");
+        let db = Database::new(&path.to_string_lossy()).unwrap();
+        db.create_collection(&collection("api", None, json!({"name": "My API"})))
+            .unwrap();
+        db.create_collection(&collection("users", Some("api"), json!({"name": "Users"})))
+            .unwrap();
+        let variables = json!([{"key": "host", "value": host}]).to_string();
+        db.save_environment(&Environment {
+            id: "env-1".to_string(),
+            name: "dev".to_string(),
+            variables,
+            is_active: false,
+            created_at: String::new(),
+            dotenv_path: None,
+            parent_id: None,
+        })
+        .unwrap();
+        for (id, name, status) in [("r1", "Get user", "200"), ("r2", "Create user", "201")] {
+            let assertions = json!([{"source": "status", "operator": "equals", "value": status}]);
+            let request: Request = serde_json::from_value(json!({
+                "id": id,
+                "collection_id": "users",
+                "name": name,
+                "method": "GET",
+                "url": "{{host}}/users/{{id}}",
+                "headers": "[]",
+                "body": "",
+                "body_type": "none",
+                "auth_type": "none",
+                "auth_data": "{}",
+                "created_at": id,
+                "updated_at": id,
+                "assertions": assertions.to_string(),
+            }))
+            .unwrap();
+            db.save_request(&request).unwrap();
+        }
+        drop(db);
+        let db = path.to_string_lossy().to_string();
+        let send = |request: &str, extra: &[&str]| {
+            let mut args = vec![request, "--db", &db, "--env", "dev", "--var", "id=1"];
+            args.extend(extra);
+            send_command(send_args(&args).unwrap())
+        };
+
+        assert_eq!(send("My API/Users/Get user", &["--json"]).await, Ok(0));
+        assert_eq!(send(" My API / Users/ Get user", &[]).await, Ok(0));
+        assert_eq!(send("r1", &[]).await, Ok(0));
+        assert_eq!(send("My API/Users/Create user", &[]).await, Ok(1));
+        assert_eq!(
+            send("My API/Users/Delete user", &[]).await,
+            Err("No saved request named 'My API/Users/Delete user'".to_string())
+        );
+        assert_eq!(
+            send("My API/Admin/Get user", &[]).await,
+            Err("No collection or folder named 'My API/Admin'".to_string())
+        );
+        assert_eq!(
+            send("r1", &["--env", "prod"]).await,
+            Err("No environment named 'prod'".to_string())
+        );
+
+        let missing = dir.join("missing.db").to_string_lossy().to_string();
+        let error = send_command(send_args(&["r1", "--db", &missing]).unwrap())
+            .await
+            .unwrap_err();
+        assert!(error.starts_with("No fetchr database at "), "{}", error);
+        assert!(send_args(&["r1", "--var", "id"]).is_err());
+        assert!(send_args(&["r1", "--var", "=1"]).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
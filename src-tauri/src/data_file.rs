@@ -0,0 +1,194 @@
+use crate::http_client::KeyValue;
+use serde_json::Value;
+use std::path::Path;
+
+// Reads a collection runner data file: a CSV file with a header row, or a
+// JSON array of objects. Each row is one iteration's variables.
+pub fn read_data_file(path: &str) -> Result<Vec<Vec<KeyValue>>, String> {
+    let text = std::fs::read_to_string(path.trim())
+        .map_err(|e| format!("Cannot read data file {}: {}", path, e))?;
+    let extension = Path::new(path.trim())
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase());
+    let is_json = match extension.as_deref() {
+        Some("json") => true,
+        Some("csv") => false,
+        _ => text.trim_start().starts_with('['),
+    };
+
+    let rows = if is_json {
+        parse_json(&text)
+    } else {
+        parse_csv(&text)
+    }
+    .map_err(|e| format!("Invalid data file {}: {}", path, e))?;
+    if rows.is_empty() {
+        return Err(format!("Data file {} has no rows", path));
+    }
+    Ok(rows)
+}
+
+fn parse_csv(text: &str) -> Result<Vec<Vec<KeyValue>>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::Headers)
+        .from_reader(text.as_bytes());
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+    if headers.iter().any(|h| h.is_empty()) {
+        return Err("every column needs a name in the header row".to_string());
+    }
+
+    reader
+        .records()
+        .map(|record| {
+            let record = record.map_err(|e| e.to_string())?;
+            Ok(headers
+                .iter()
+                .zip(record.iter())
+                .map(|(key, value)| row_value(key, value.to_string()))
+                .collect())
+        })
+        .collect()
+}
+
+// Strings are used as-is, null as an empty string and anything else as JSON
+fn parse_json(text: &str) -> Result<Vec<Vec<KeyValue>>, String> {
+    let rows: Vec<Value> =
+        serde_json::from_str(text).map_err(|e| format!("expected an array of objects: {}", e))?;
+    rows.into_iter()
+        .enumerate()
+        .map(|(i, row)| match row {
+            Value::Object(map) => Ok(map
+                .into_iter()
+                .map(|(key, value)| {
+                    let value = match value {
+                        Value::String(s) => s,
+                        Value::Null => String::new(),
+                        other => other.to_string(),
+                    };
+                    row_value(&key, value)
+                })
+                .collect()),
+            _ => Err(format!("row {} is not an object", i + 1)),
+        })
+        .collect()
+}
+
+fn row_value(key: &str, value: String) -> KeyValue {
+    KeyValue {
+        key: key.to_string(),
+        value,
+        enabled: true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(rows: Vec<Vec<KeyValue>>) -> Vec<Vec<(String, String)>> {
+        rows.into_iter()
+            .map(|row| row.into_iter().map(|kv| (kv.key, kv.value)).collect())
+            .collect()
+    }
+
+    fn row(values: &[(&str, &str)]) -> Vec<(String, String)> {
+        values
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    // Writes `content` to a fresh temporary file with the given name
+    fn temp_file(name: &str, content: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("fetchr-data-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn parses_csv_rows() {
+        let rows =
+            parse_csv(" email , name\nalice@example.com,\"Smith, Alice\"\nbob@example.com,Bob\n")
+                .unwrap();
+        assert_eq!(
+            pairs(rows),
+            vec![
+                row(&[("email", "alice@example.com"), ("name", "Smith, Alice")]),
+                row(&[("email", "bob@example.com"), ("name", "Bob")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_csv() {
+        assert_eq!(
+            parse_csv("email,\na,b\n").unwrap_err(),
+            "every column needs a name in the header row"
+        );
+        assert!(parse_csv("a,b\n1,2,3\n").is_err());
+    }
+
+    #[test]
+    fn parses_json_rows() {
+        let rows = parse_json(
+            r#"[{"id": 1, "name": "alice", "tags": ["a"], "note": null}, {"active": true}]"#,
+        )
+        .unwrap();
+        let mut first = pairs(rows.clone()).remove(0);
+        first.sort();
+        assert_eq!(
+            first,
+            row(&[
+                ("id", "1"),
+                ("name", "alice"),
+                ("note", ""),
+                ("tags", r#"["a"]"#)
+            ])
+        );
+        assert_eq!(pairs(rows)[1], row(&[("active", "true")]));
+    }
+
+    #[test]
+    fn rejects_bad_json() {
+        assert_eq!(
+            parse_json(r#"[{"a": 1}, 2]"#).unwrap_err(),
+            "row 2 is not an object"
+        );
+        assert!(parse_json(r#"{"a": 1}"#)
+            .unwrap_err()
+            .starts_with("expected an array of objects"));
+    }
+
+    #[test]
+    fn reads_by_extension_or_content() {
+        let csv = temp_file("users.csv", "id\n1\n2\n");
+        assert_eq!(read_data_file(&csv).unwrap().len(), 2);
+
+        let json = temp_file("users.json", r#"[{"id": 1}]"#);
+        assert_eq!(
+            pairs(read_data_file(&json).unwrap()),
+            vec![row(&[("id", "1")])]
+        );
+
+        // Without a known extension, a leading '[' means JSON
+        let sniffed = temp_file("users.txt", "  [{\"id\": \"x\"}]");
+        assert_eq!(
+            pairs(read_data_file(&sniffed).unwrap()),
+            vec![row(&[("id", "x")])]
+        );
+    }
+
+    #[test]
+    fn reports_empty_and_missing_files() {
+        let empty = temp_file("empty.csv", "id\n");
+        assert_eq!(
+            read_data_file(&empty).unwrap_err(),
+            format!("Data file {} has no rows", empty)
+        );
+        assert!(read_data_file("/nonexistent/data.csv")
+            .unwrap_err()
+            .starts_with("Cannot read data file /nonexistent/data.csv"));
+    }
+}
@@ -13,6 +13,32 @@ pub struct Collection {
     pub parent_id: Option<String>,
     pub is_folder: bool,
     pub created_at: String,
+    #[serde(default = "default_inherit")]
+    pub auth_type: String,
+    #[serde(default = "default_object")]
+    pub auth_data: String, // JSON string
+    #[serde(default = "default_array")]
+    pub headers: String, // JSON string
+    #[serde(default = "default_array")]
+    pub variables: String, // JSON string
+    #[serde(default)]
+    pub pre_request_script: String,
+    #[serde(default)]
+    pub test_script: String,
+    #[serde(default = "default_array")]
+    pub schemas: String, // JSON string of named JSON Schemas
+}
+
+fn default_inherit() -> String {
+    "inherit".to_string()
+}
+
+fn default_object() -> String {
+    "{}".to_string()
+}
+
+fn default_array() -> String {
+    "[]".to_string()
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -29,6 +55,18 @@ pub struct Request {
     pub auth_data: String, // JSON string
     pub created_at: String,
     pub updated_at: String,
+    #[serde(default = "default_array")]
+    pub variables: String, // JSON string of request-local variables
+    #[serde(default)]
+    pub pre_request_script: String,
+    #[serde(default)]
+    pub test_script: String,
+    #[serde(default = "default_array")]
+    pub assertions: String, // JSON string of declarative response assertions
+    #[serde(default = "default_array")]
+    pub extractions: String, // JSON string of response extraction rules
+    #[serde(default)]
+    pub response_schema: String, // JSON string of the schema reference, empty for none
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -38,6 +76,10 @@ pub struct Environment {
     pub variables: String, // JSON string
     pub is_active: bool,
     pub created_at: String,
+    #[serde(default)]
+    pub dotenv_path: Option<String>, // .env file read on every send
+    #[serde(default)]
+    pub parent_id: Option<String>, // environment whose variables this one overrides
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -50,6 +92,83 @@ pub struct History {
     pub created_at: String,
 }
 
+const GLOBAL_VARIABLES_SETTING: &str = "global_variables";
+
+const COLLECTION_COLUMNS: &str = "id, name, parent_id, is_folder, created_at, auth_type, auth_data, headers, variables,
+     pre_request_script, test_script, schemas";
+
+fn collection_from_row(row: &rusqlite::Row) -> Result<Collection> {
+    Ok(Collection {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        parent_id: row.get(2)?,
+        is_folder: row.get::<_, i32>(3)? != 0,
+        created_at: row.get(4)?,
+        auth_type: row.get(5)?,
+        auth_data: row.get(6)?,
+        headers: row.get(7)?,
+        variables: row.get(8)?,
+        pre_request_script: row.get(9)?,
+        test_script: row.get(10)?,
+        schemas: row.get(11)?,
+    })
+}
+
+const REQUEST_COLUMNS: &str = "id, collection_id, name, method, url, headers, body, body_type, auth_type, auth_data,
+     created_at, updated_at, variables, pre_request_script, test_script, assertions, extractions, response_schema";
+
+fn request_from_row(row: &rusqlite::Row) -> Result<Request> {
+    Ok(Request {
+        id: row.get(0)?,
+        collection_id: row.get(1)?,
+        name: row.get(2)?,
+        method: row.get(3)?,
+        url: row.get(4)?,
+        headers: row.get(5)?,
+        body: row.get(6)?,
+        body_type: row.get(7)?,
+        auth_type: row.get(8)?,
+        auth_data: row.get(9)?,
+        created_at: row.get(10)?,
+        updated_at: row.get(11)?,
+        variables: row.get(12)?,
+        pre_request_script: row.get(13)?,
+        test_script: row.get(14)?,
+        assertions: row.get(15)?,
+        extractions: row.get(16)?,
+        response_schema: row.get(17)?,
+    })
+}
+
+const ENVIRONMENT_COLUMNS: &str =
+    "id, name, variables, is_active, created_at, dotenv_path, parent_id";
+
+fn environment_from_row(row: &rusqlite::Row) -> Result<Environment> {
+    Ok(Environment {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        variables: row.get(2)?,
+        is_active: row.get::<_, i32>(3)? != 0,
+        created_at: row.get(4)?,
+        dotenv_path: row.get(5)?,
+        parent_id: row.get(6)?,
+    })
+}
+
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>>>()?;
+    if !columns.iter().any(|c| c == column) {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
+    Ok(())
+}
+
 impl Database {
     pub fn new(path: &str) -> Result<Self> {
         let conn = Connection::open(path)?;
@@ -69,7 +188,14 @@ impl Database {
                 name TEXT NOT NULL,
                 parent_id TEXT,
                 is_folder INTEGER NOT NULL,
-                created_at TEXT NOT NULL
+                created_at TEXT NOT NULL,
+                auth_type TEXT NOT NULL DEFAULT 'inherit',
+                auth_data TEXT NOT NULL DEFAULT '{}',
+                headers TEXT NOT NULL DEFAULT '[]',
+                variables TEXT NOT NULL DEFAULT '[]',
+                pre_request_script TEXT NOT NULL DEFAULT '',
+                test_script TEXT NOT NULL DEFAULT '',
+                schemas TEXT NOT NULL DEFAULT '[]'
             )",
             [],
         )?;
@@ -88,6 +214,12 @@ impl Database {
                 auth_data TEXT NOT NULL DEFAULT '{}',
                 created_at TEXT NOT NULL,
                 updated_at TEXT NOT NULL,
+                variables TEXT NOT NULL DEFAULT '[]',
+                pre_request_script TEXT NOT NULL DEFAULT '',
+                test_script TEXT NOT NULL DEFAULT '',
+                assertions TEXT NOT NULL DEFAULT '[]',
+                extractions TEXT NOT NULL DEFAULT '[]',
+                response_schema TEXT NOT NULL DEFAULT '',
                 FOREIGN KEY (collection_id) REFERENCES collections(id) ON DELETE CASCADE
             )",
             [],
@@ -99,7 +231,9 @@ impl Database {
                 name TEXT NOT NULL,
                 variables TEXT NOT NULL DEFAULT '[]',
                 is_active INTEGER NOT NULL DEFAULT 0,
-                created_at TEXT NOT NULL
+                created_at TEXT NOT NULL,
+                dotenv_path TEXT,
+                parent_id TEXT
             )",
             [],
         )?;
@@ -116,6 +250,31 @@ impl Database {
             [],
         )?;
 
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        // Columns added after the initial schema
+        add_column_if_missing(&conn, "collections", "auth_type", "TEXT NOT NULL DEFAULT 'inherit'")?;
+        add_column_if_missing(&conn, "collections", "auth_data", "TEXT NOT NULL DEFAULT '{}'")?;
+        add_column_if_missing(&conn, "collections", "headers", "TEXT NOT NULL DEFAULT '[]'")?;
+        add_column_if_missing(&conn, "collections", "variables", "TEXT NOT NULL DEFAULT '[]'")?;
+        add_column_if_missing(&conn, "requests", "variables", "TEXT NOT NULL DEFAULT '[]'")?;
+        add_column_if_missing(&conn, "environments", "dotenv_path", "TEXT")?;
+        add_column_if_missing(&conn, "environments", "parent_id", "TEXT")?;
+        for table in ["collections", "requests"] {
+            add_column_if_missing(&conn, table, "pre_request_script", "TEXT NOT NULL DEFAULT ''")?;
+            add_column_if_missing(&conn, table, "test_script", "TEXT NOT NULL DEFAULT ''")?;
+        }
+        add_column_if_missing(&conn, "requests", "assertions", "TEXT NOT NULL DEFAULT '[]'")?;
+        add_column_if_missing(&conn, "requests", "extractions", "TEXT NOT NULL DEFAULT '[]'")?;
+        add_column_if_missing(&conn, "requests", "response_schema", "TEXT NOT NULL DEFAULT ''")?;
+        add_column_if_missing(&conn, "collections", "schemas", "TEXT NOT NULL DEFAULT '[]'")?;
+
         Ok(())
     }
 
@@ -123,13 +282,43 @@ impl Database {
     pub fn create_collection(&self, collection: &Collection) -> Result<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "INSERT INTO collections (id, name, parent_id, is_folder, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            "INSERT INTO collections (id, name, parent_id, is_folder, created_at, auth_type, auth_data, headers, variables,
+             pre_request_script, test_script, schemas)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
             params![
                 collection.id,
                 collection.name,
                 collection.parent_id,
                 collection.is_folder as i32,
-                collection.created_at
+                collection.created_at,
+                collection.auth_type,
+                collection.auth_data,
+                collection.headers,
+                collection.variables,
+                collection.pre_request_script,
+                collection.test_script,
+                collection.schemas
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_collection(&self, collection: &Collection) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE collections SET name = ?2, parent_id = ?3, auth_type = ?4, auth_data = ?5, headers = ?6,
+             variables = ?7, pre_request_script = ?8, test_script = ?9, schemas = ?10 WHERE id = ?1",
+            params![
+                collection.id,
+                collection.name,
+                collection.parent_id,
+                collection.auth_type,
+                collection.auth_data,
+                collection.headers,
+                collection.variables,
+                collection.pre_request_script,
+                collection.test_script,
+                collection.schemas
             ],
         )?;
         Ok(())
@@ -137,21 +326,52 @@ impl Database {
 
     pub fn get_all_collections(&self) -> Result<Vec<Collection>> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, name, parent_id, is_folder, created_at FROM collections ORDER BY created_at")?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM collections ORDER BY created_at",
+            COLLECTION_COLUMNS
+        ))?;
         let collections = stmt
-            .query_map([], |row| {
-                Ok(Collection {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    parent_id: row.get(2)?,
-                    is_folder: row.get::<_, i32>(3)? != 0,
-                    created_at: row.get(4)?,
-                })
-            })?
+            .query_map([], collection_from_row)?
             .collect::<Result<Vec<_>>>()?;
         Ok(collections)
     }
 
+    pub fn get_collection(&self, id: &str) -> Result<Option<Collection>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM collections WHERE id = ?1",
+            COLLECTION_COLUMNS
+        ))?;
+        let mut rows = stmt.query(params![id])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(collection_from_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // Returns the collection and its ancestors, nearest first
+    pub fn get_collection_chain(&self, collection_id: &str) -> Result<Vec<Collection>> {
+        let mut chain: Vec<Collection> = Vec::new();
+        let mut next = Some(collection_id.to_string());
+
+        while let Some(id) = next {
+            // Guard against parent_id loops
+            if chain.iter().any(|c| c.id == id) {
+                break;
+            }
+            match self.get_collection(&id)? {
+                Some(collection) => {
+                    next = collection.parent_id.clone();
+                    chain.push(collection);
+                }
+                None => break,
+            }
+        }
+
+        Ok(chain)
+    }
+
     pub fn delete_collection(&self, id: &str) -> Result<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute("DELETE FROM collections WHERE id = ?1", params![id])?;
@@ -162,8 +382,9 @@ impl Database {
     pub fn save_request(&self, request: &Request) -> Result<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "INSERT OR REPLACE INTO requests (id, collection_id, name, method, url, headers, body, body_type, auth_type, auth_data, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            "INSERT OR REPLACE INTO requests (id, collection_id, name, method, url, headers, body, body_type, auth_type, auth_data, created_at, updated_at, variables,
+             pre_request_script, test_script, assertions, extractions, response_schema)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
             params![
                 request.id,
                 request.collection_id,
@@ -176,7 +397,13 @@ impl Database {
                 request.auth_type,
                 request.auth_data,
                 request.created_at,
-                request.updated_at
+                request.updated_at,
+                request.variables,
+                request.pre_request_script,
+                request.test_script,
+                request.assertions,
+                request.extractions,
+                request.response_schema
             ],
         )?;
         Ok(())
@@ -184,53 +411,22 @@ impl Database {
 
     pub fn get_requests_by_collection(&self, collection_id: &str) -> Result<Vec<Request>> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, collection_id, name, method, url, headers, body, body_type, auth_type, auth_data, created_at, updated_at
-             FROM requests WHERE collection_id = ?1 ORDER BY created_at"
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM requests WHERE collection_id = ?1 ORDER BY created_at",
+            REQUEST_COLUMNS
+        ))?;
         let requests = stmt
-            .query_map(params![collection_id], |row| {
-                Ok(Request {
-                    id: row.get(0)?,
-                    collection_id: row.get(1)?,
-                    name: row.get(2)?,
-                    method: row.get(3)?,
-                    url: row.get(4)?,
-                    headers: row.get(5)?,
-                    body: row.get(6)?,
-                    body_type: row.get(7)?,
-                    auth_type: row.get(8)?,
-                    auth_data: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
-                })
-            })?
+            .query_map(params![collection_id], request_from_row)?
             .collect::<Result<Vec<_>>>()?;
         Ok(requests)
     }
 
     pub fn get_request(&self, id: &str) -> Result<Option<Request>> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, collection_id, name, method, url, headers, body, body_type, auth_type, auth_data, created_at, updated_at
-             FROM requests WHERE id = ?1"
-        )?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM requests WHERE id = ?1", REQUEST_COLUMNS))?;
         let mut rows = stmt.query(params![id])?;
         if let Some(row) = rows.next()? {
-            Ok(Some(Request {
-                id: row.get(0)?,
-                collection_id: row.get(1)?,
-                name: row.get(2)?,
-                method: row.get(3)?,
-                url: row.get(4)?,
-                headers: row.get(5)?,
-                body: row.get(6)?,
-                body_type: row.get(7)?,
-                auth_type: row.get(8)?,
-                auth_data: row.get(9)?,
-                created_at: row.get(10)?,
-                updated_at: row.get(11)?,
-            }))
+            Ok(Some(request_from_row(row)?))
         } else {
             Ok(None)
         }
@@ -252,14 +448,16 @@ impl Database {
         }
 
         conn.execute(
-            "INSERT OR REPLACE INTO environments (id, name, variables, is_active, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
+            "INSERT OR REPLACE INTO environments (id, name, variables, is_active, created_at, dotenv_path, parent_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
             params![
                 env.id,
                 env.name,
                 env.variables,
                 env.is_active as i32,
-                env.created_at
+                env.created_at,
+                env.dotenv_path,
+                env.parent_id
             ],
         )?;
         Ok(())
@@ -267,40 +465,74 @@ impl Database {
 
     pub fn get_all_environments(&self) -> Result<Vec<Environment>> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, name, variables, is_active, created_at FROM environments ORDER BY created_at")?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM environments ORDER BY created_at",
+            ENVIRONMENT_COLUMNS
+        ))?;
         let envs = stmt
-            .query_map([], |row| {
-                Ok(Environment {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    variables: row.get(2)?,
-                    is_active: row.get::<_, i32>(3)? != 0,
-                    created_at: row.get(4)?,
-                })
-            })?
+            .query_map([], environment_from_row)?
             .collect::<Result<Vec<_>>>()?;
         Ok(envs)
     }
 
     pub fn get_active_environment(&self) -> Result<Option<Environment>> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, name, variables, is_active, created_at FROM environments WHERE is_active = 1")?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM environments WHERE is_active = 1",
+            ENVIRONMENT_COLUMNS
+        ))?;
         let mut rows = stmt.query([])?;
         if let Some(row) = rows.next()? {
-            Ok(Some(Environment {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                variables: row.get(2)?,
-                is_active: row.get::<_, i32>(3)? != 0,
-                created_at: row.get(4)?,
-            }))
+            Ok(Some(environment_from_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn get_environment(&self, id: &str) -> Result<Option<Environment>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM environments WHERE id = ?1",
+            ENVIRONMENT_COLUMNS
+        ))?;
+        let mut rows = stmt.query(params![id])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(environment_from_row(row)?))
         } else {
             Ok(None)
         }
     }
 
+    // Returns the environment followed by its parents, nearest first
+    pub fn get_environment_chain(&self, id: &str) -> Result<Vec<Environment>> {
+        let mut chain: Vec<Environment> = Vec::new();
+        let mut next = Some(id.to_string());
+
+        while let Some(id) = next {
+            // Guard against parent_id loops
+            if chain.iter().any(|e| e.id == id) {
+                break;
+            }
+            match self.get_environment(&id)? {
+                Some(env) => {
+                    next = env.parent_id.clone();
+                    chain.push(env);
+                }
+                None => break,
+            }
+        }
+
+        Ok(chain)
+    }
+
     pub fn delete_environment(&self, id: &str) -> Result<()> {
         let conn = self.conn.lock().unwrap();
+        // Children inherit from the deleted environment's parent instead
+        conn.execute(
+            "UPDATE environments SET parent_id = (SELECT parent_id FROM environments WHERE id = ?1)
+             WHERE parent_id = ?1",
+            params![id],
+        )?;
         conn.execute("DELETE FROM environments WHERE id = ?1", params![id])?;
         Ok(())
     }
@@ -348,4 +580,36 @@ impl Database {
         conn.execute("DELETE FROM history", [])?;
         Ok(())
     }
+
+    // Global variables, stored in the same JSON shape as environment variables
+    pub fn get_global_variables(&self) -> Result<String> {
+        Ok(self
+            .get_setting(GLOBAL_VARIABLES_SETTING)?
+            .unwrap_or_else(default_array))
+    }
+
+    pub fn save_global_variables(&self, variables: &str) -> Result<()> {
+        self.set_setting(GLOBAL_VARIABLES_SETTING, variables)
+    }
+
+    // Settings
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT value FROM settings WHERE key = ?1")?;
+        let mut rows = stmt.query(params![key])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(row.get(0)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )?;
+        Ok(())
+    }
 }
@@ -0,0 +1,304 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chrono::{Duration, SecondsFormat, Utc};
+use rand::distributions::Alphanumeric;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+const FIRST_NAMES: [&str; 12] = [
+    "alex", "sam", "jordan", "taylor", "morgan", "casey", "riley", "jamie", "avery", "quinn",
+    "rowan", "drew",
+];
+const LAST_NAMES: [&str; 12] = [
+    "smith", "garcia", "chen", "kowalski", "okafor", "novak", "silva", "tanaka", "meyer", "dubois",
+    "khan", "larsen",
+];
+const DOMAINS: [&str; 4] = ["example.com", "example.org", "example.net", "test.local"];
+
+// Evaluates a built-in `$` variable, given the expression after the `$`.
+// Arguments follow the name separated by whitespace, e.g. `randomInt 1 6`;
+// timestamps also take an offset such as `timestamp+1h` or `isoTimestamp -30m`.
+// Returns None when the name is not a built-in.
+pub fn evaluate(
+    expression: &str,
+    lookup: &mut dyn FnMut(&str) -> Option<String>,
+) -> Option<Result<String, String>> {
+    // Process environment variables, e.g. {{$env.HOME}}
+    if let Some(key) = expression.strip_prefix("env.") {
+        return Some(
+            std::env::var(key.trim())
+                .map_err(|_| format!("environment variable '{}' is not set", key.trim())),
+        );
+    }
+
+    let split = expression
+        .find(|c: char| c.is_whitespace() || c == '+' || c == '-')
+        .unwrap_or(expression.len());
+    let (name, args) = expression.split_at(split);
+    let args: Vec<&str> = args.split_whitespace().collect();
+
+    let value = match name {
+        // UUIDs
+        "guid" | "uuid" | "randomUUID" => no_args(name, &args, || uuid::Uuid::new_v4().to_string()),
+        "uuidv7" => no_args(name, &args, || uuid::Uuid::now_v7().to_string()),
+
+        // Timestamps
+        "timestamp" => offset_now(&args).map(|t| t.timestamp().to_string()),
+        "timestampMs" => offset_now(&args).map(|t| t.timestamp_millis().to_string()),
+        "isoTimestamp" => offset_now(&args).map(|t| t.to_rfc3339_opts(SecondsFormat::Millis, true)),
+
+        // Random values
+        "randomInt" => random_int(&args),
+        "randomString" => random_string(&args),
+        "randomAlphaNumeric" => no_args(name, &args, || random_chars(1)),
+        "randomBoolean" => no_args(name, &args, || rand::thread_rng().gen_bool(0.5).to_string()),
+        "randomFirstName" => no_args(name, &args, || capitalize(pick(&FIRST_NAMES))),
+        "randomLastName" => no_args(name, &args, || capitalize(pick(&LAST_NAMES))),
+        "randomUserName" => no_args(name, &args, random_user_name),
+        "randomEmail" | "randomExampleEmail" => no_args(name, &args, || {
+            format!("{}@{}", random_user_name(), pick(&DOMAINS))
+        }),
+
+        // Encodings of other variables
+        "base64" => match args.as_slice() {
+            [key] => lookup(key)
+                .map(|value| BASE64.encode(value))
+                .ok_or_else(|| format!("unknown variable '{}'", key)),
+            _ => Err("$base64 takes a variable name".to_string()),
+        },
+
+        _ => return None,
+    };
+
+    Some(value)
+}
+
+fn no_args(name: &str, args: &[&str], f: impl FnOnce() -> String) -> Result<String, String> {
+    if args.is_empty() {
+        Ok(f())
+    } else {
+        Err(format!("${} takes no arguments", name))
+    }
+}
+
+// Offsets are a signed number with a unit: s, m, h, d or w
+fn offset_now(args: &[&str]) -> Result<chrono::DateTime<Utc>, String> {
+    let mut now = Utc::now();
+    for arg in args {
+        let (sign, amount) = match (arg.strip_prefix('+'), arg.strip_prefix('-')) {
+            (Some(rest), _) => (1, rest),
+            (_, Some(rest)) => (-1, rest),
+            _ => return Err(format!("offset '{}' must start with + or -", arg)),
+        };
+        let unit_at = amount
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(|| format!("offset '{}' is missing a unit", arg))?;
+        let (number, unit) = amount.split_at(unit_at);
+        let number: i64 = number
+            .parse()
+            .map_err(|_| format!("offset '{}' is not a number", arg))?;
+
+        let duration = match unit {
+            "s" => Duration::try_seconds(number),
+            "m" => Duration::try_minutes(number),
+            "h" => Duration::try_hours(number),
+            "d" => Duration::try_days(number),
+            "w" => Duration::try_weeks(number),
+            _ => return Err(format!("unknown offset unit '{}'", unit)),
+        }
+        .ok_or_else(|| format!("offset '{}' is out of range", arg))?;
+
+        now = now
+            .checked_add_signed(duration * sign)
+            .ok_or_else(|| format!("offset '{}' is out of range", arg))?;
+    }
+    Ok(now)
+}
+
+// Postman's $randomInt is 0..=1000; a range may be given as `min max`
+fn random_int(args: &[&str]) -> Result<String, String> {
+    let (min, max) = match args {
+        [] => (0, 1000),
+        [min, max] => (
+            min.parse::<i64>()
+                .map_err(|_| format!("'{}' is not an integer", min))?,
+            max.parse::<i64>()
+                .map_err(|_| format!("'{}' is not an integer", max))?,
+        ),
+        _ => return Err("$randomInt takes no arguments or a min and max".to_string()),
+    };
+    if min > max {
+        return Err(format!("min {} is greater than max {}", min, max));
+    }
+    Ok(rand::thread_rng().gen_range(min..=max).to_string())
+}
+
+fn random_string(args: &[&str]) -> Result<String, String> {
+    let length = match args {
+        [] => 16,
+        [length] => length
+            .parse::<usize>()
+            .ok()
+            .filter(|l| *l <= 4096)
+            .ok_or_else(|| format!("'{}' is not a valid length", length))?,
+        _ => return Err("$randomString takes an optional length".to_string()),
+    };
+    Ok(random_chars(length))
+}
+
+fn random_chars(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+fn random_user_name() -> String {
+    format!(
+        "{}.{}{}",
+        pick(&FIRST_NAMES),
+        pick(&LAST_NAMES),
+        rand::thread_rng().gen_range(1..100)
+    )
+}
+
+fn pick(options: &[&'static str]) -> &'static str {
+    options
+        .choose(&mut rand::thread_rng())
+        .copied()
+        .unwrap_or_default()
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(expression: &str) -> Option<Result<String, String>> {
+        evaluate(expression, &mut |key| {
+            (key == "user").then(|| "alice".to_string())
+        })
+    }
+
+    fn value(expression: &str) -> String {
+        eval(expression).unwrap().unwrap()
+    }
+
+    fn error(expression: &str) -> String {
+        eval(expression).unwrap().unwrap_err()
+    }
+
+    #[test]
+    fn unknown_names_are_not_built_ins() {
+        assert!(eval("nope").is_none());
+        assert!(eval("Timestamp").is_none());
+    }
+
+    #[test]
+    fn generates_uuids() {
+        let guid = value("guid");
+        assert_eq!(uuid::Uuid::parse_str(&guid).unwrap().get_version_num(), 4);
+        assert_ne!(guid, value("guid"));
+        assert_eq!(
+            uuid::Uuid::parse_str(&value("uuidv7"))
+                .unwrap()
+                .get_version_num(),
+            7
+        );
+        assert_eq!(error("uuid 1"), "$uuid takes no arguments");
+    }
+
+    #[test]
+    fn timestamps_take_offsets() {
+        let now = Utc::now().timestamp();
+        let stamp: i64 = value("timestamp").parse().unwrap();
+        assert!((stamp - now).abs() <= 1);
+
+        let later: i64 = value("timestamp+1h").parse().unwrap();
+        assert!((later - now - 3600).abs() <= 1);
+        let earlier: i64 = value("timestamp -2d +30m").parse().unwrap();
+        assert!((earlier - now + 2 * 86400 - 1800).abs() <= 1);
+
+        let ms: i64 = value("timestampMs").parse().unwrap();
+        assert!((ms / 1000 - now).abs() <= 1);
+        assert!(value("isoTimestamp").ends_with('Z'));
+    }
+
+    #[test]
+    fn rejects_bad_offsets() {
+        assert_eq!(error("timestamp 1h"), "offset '1h' must start with + or -");
+        assert_eq!(error("timestamp +h"), "offset '+h' is not a number");
+        assert_eq!(error("timestamp +5"), "offset '+5' is missing a unit");
+        assert_eq!(error("timestamp +5y"), "unknown offset unit 'y'");
+        assert_eq!(
+            error("timestamp +99999999999w"),
+            "offset '+99999999999w' is out of range"
+        );
+    }
+
+    #[test]
+    fn rejects_non_ascii_offsets() {
+        assert_eq!(error("timestamp é"), "offset 'é' must start with + or -");
+        assert_eq!(
+            error("isoTimestamp ü1h"),
+            "offset 'ü1h' must start with + or -"
+        );
+        assert_eq!(error("timestamp +1é"), "unknown offset unit 'é'");
+
+        // Reported as an issue on the placeholder rather than aborting the send
+        let mut issues = Vec::new();
+        let result = crate::interpolation::interpolate_with_issues(
+            "{{$timestamp é}}",
+            &std::collections::HashMap::new(),
+            "url",
+            &mut issues,
+        );
+        assert_eq!(result, "{{$timestamp é}}");
+        assert_eq!(issues.len(), 1);
+    }
+
+    #[test]
+    fn random_values_respect_their_arguments() {
+        for _ in 0..50 {
+            let n: i64 = value("randomInt 3 5").parse().unwrap();
+            assert!((3..=5).contains(&n));
+        }
+        let n: i64 = value("randomInt").parse().unwrap();
+        assert!((0..=1000).contains(&n));
+        assert_eq!(error("randomInt 5 3"), "min 5 is greater than max 3");
+        assert_eq!(error("randomInt a 3"), "'a' is not an integer");
+
+        assert_eq!(value("randomString").len(), 16);
+        assert_eq!(value("randomString 40").len(), 40);
+        assert_eq!(error("randomString 5000"), "'5000' is not a valid length");
+        assert!(["true", "false"].contains(&value("randomBoolean").as_str()));
+        assert!(value("randomEmail").contains('@'));
+    }
+
+    #[test]
+    fn base64_encodes_another_variable() {
+        assert_eq!(value("base64 user"), "YWxpY2U=");
+        assert_eq!(error("base64 other"), "unknown variable 'other'");
+        assert_eq!(error("base64"), "$base64 takes a variable name");
+    }
+
+    #[test]
+    fn reads_process_environment_variables() {
+        assert_eq!(value("env.CARGO_MANIFEST_DIR"), env!("CARGO_MANIFEST_DIR"));
+        assert_eq!(
+            value("env. CARGO_MANIFEST_DIR "),
+            env!("CARGO_MANIFEST_DIR")
+        );
+        assert_eq!(
+            error("env.FETCHR_UNSET_VARIABLE"),
+            "environment variable 'FETCHR_UNSET_VARIABLE' is not set"
+        );
+    }
+}
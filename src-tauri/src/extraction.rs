@@ -0,0 +1,355 @@
+use crate::http_client::HttpResponse;
+use crate::variables::{VariableScope, VariableUpdate};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_json_path::JsonPath;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtractionSource {
+    JsonPath,
+    #[serde(rename = "xpath")]
+    XPath,
+    Regex,
+    Header,
+    Cookie,
+    Status,
+}
+
+// Copies a value from the response into a variable, e.g. `$.token` into `{{token}}`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExtractionRule {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub source: ExtractionSource,
+    // JSON path, XPath, regex, header or cookie name; unused for status
+    #[serde(default)]
+    pub expression: String,
+    pub variable: String,
+    pub scope: VariableScope,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExtractionResult {
+    pub variable: String,
+    pub scope: VariableScope,
+    pub value: Option<String>,
+    pub error: Option<String>,
+}
+
+impl ExtractionResult {
+    pub fn update(&self) -> Option<VariableUpdate> {
+        self.value.as_ref().map(|value| VariableUpdate {
+            scope: self.scope,
+            key: self.variable.clone(),
+            value: Some(value.clone()),
+        })
+    }
+}
+
+impl ExtractionRule {
+    fn extract(&self, response: &HttpResponse) -> Result<String, String> {
+        let expression = self.expression.trim();
+        match self.source {
+            ExtractionSource::Status => Ok(response.status.to_string()),
+            ExtractionSource::Header => response
+                .headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(expression))
+                .map(|(_, value)| value.clone())
+                .ok_or_else(|| format!("no '{}' header in the response", expression)),
+            ExtractionSource::Cookie => response
+                .cookies
+                .iter()
+                .find(|c| c.name == expression)
+                .map(|c| c.value.clone())
+                .ok_or_else(|| format!("no '{}' cookie in the response", expression)),
+            ExtractionSource::JsonPath => {
+                let path = JsonPath::parse(expression)
+                    .map_err(|e| format!("invalid JSON path '{}': {}", expression, e))?;
+                let body: Value = serde_json::from_str(&response.body)
+                    .map_err(|_| "response body is not JSON".to_string())?;
+                let value = path
+                    .query(&body)
+                    .all()
+                    .first()
+                    .map(|v| match v {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    })
+                    .ok_or_else(|| format!("'{}' matched nothing", expression))?;
+                Ok(value)
+            }
+            ExtractionSource::XPath => {
+                let package = sxd_document::parser::parse(&response.body)
+                    .map_err(|_| "response body is not XML".to_string())?;
+                let document = package.as_document();
+                let value = sxd_xpath::evaluate_xpath(&document, expression)
+                    .map_err(|e| format!("invalid XPath '{}': {}", expression, e))?;
+                if let sxd_xpath::Value::Nodeset(nodes) = &value
+                    && nodes.size() == 0
+                {
+                    return Err(format!("'{}' matched nothing", expression));
+                }
+                Ok(value.string())
+            }
+            // The first capture group, or the whole match when there is none
+            ExtractionSource::Regex => {
+                let pattern = Regex::new(expression)
+                    .map_err(|e| format!("invalid regex '{}': {}", expression, e))?;
+                let captures = pattern
+                    .captures(&response.body)
+                    .ok_or_else(|| format!("'{}' matched nothing", expression))?;
+                let found = captures.get(1).or_else(|| captures.get(0));
+                Ok(found.map(|m| m.as_str().to_string()).unwrap_or_default())
+            }
+        }
+    }
+}
+
+// Runs the enabled rules against a successful (2xx) response. Rules fail
+// when the send has no collection or environment for their scope.
+pub fn extract_values(
+    rules: &[ExtractionRule],
+    response: &HttpResponse,
+    has_collection: bool,
+    has_environment: bool,
+) -> Vec<ExtractionResult> {
+    rules
+        .iter()
+        .filter(|rule| rule.enabled)
+        .map(|rule| {
+            let value = if !(200..300).contains(&response.status) {
+                Err(format!("skipped, response status was {}", response.status))
+            } else if rule.scope == VariableScope::Collection && !has_collection {
+                Err("request is not saved in a collection".to_string())
+            } else if rule.scope == VariableScope::Environment && !has_environment {
+                Err("no environment selected".to_string())
+            } else {
+                rule.extract(response)
+            };
+            let (value, error) = match value {
+                Ok(value) => (Some(value), None),
+                Err(error) => (None, Some(error)),
+            };
+            ExtractionResult {
+                variable: rule.variable.clone(),
+                scope: rule.scope,
+                value,
+                error,
+            }
+        })
+        .collect()
+}
+
+// Checks a saved rule list before it is stored
+pub fn validate_extractions(json: &str) -> Result<(), String> {
+    let rules: Vec<ExtractionRule> =
+        serde_json::from_str(json).map_err(|e| format!("Invalid extraction rules: {}", e))?;
+    for rule in rules.iter().filter(|r| r.enabled) {
+        let variable = rule.variable.trim();
+        if variable.is_empty() {
+            return Err("Extraction rules need a variable name".to_string());
+        }
+        if rule.scope == VariableScope::Data {
+            return Err(format!(
+                "Extraction rule for '{}' cannot save to iteration data",
+                variable
+            ));
+        }
+        if variable.contains("{{") || variable.contains("}}") || variable.contains('|') {
+            return Err(format!(
+                "Variable name '{}' cannot contain '{{{{', '}}}}' or '|'",
+                variable
+            ));
+        }
+        let expression = rule.expression.trim();
+        match rule.source {
+            ExtractionSource::Status => {}
+            _ if expression.is_empty() => {
+                return Err(format!(
+                    "Extraction rule for '{}' has no expression",
+                    variable
+                ));
+            }
+            ExtractionSource::JsonPath => {
+                JsonPath::parse(expression)
+                    .map_err(|e| format!("Invalid JSON path '{}': {}", expression, e))?;
+            }
+            ExtractionSource::XPath => {
+                sxd_xpath::Factory::new()
+                    .build(expression)
+                    .map_err(|e| format!("Invalid XPath '{}': {}", expression, e))?;
+            }
+            ExtractionSource::Regex => {
+                Regex::new(expression)
+                    .map_err(|e| format!("Invalid regex '{}': {}", expression, e))?;
+            }
+            ExtractionSource::Header | ExtractionSource::Cookie => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn response(status: u16, body: &str) -> HttpResponse {
+        serde_json::from_value(json!({
+            "status": status,
+            "status_text": "",
+            "headers": {"X-Request-Id": "req-1"},
+            "body": body,
+            "response_time": 10,
+            "size": body.len(),
+            "cookies": [{"name": "session", "value": "abc123", "domain": null, "path": "/"}],
+        }))
+        .unwrap()
+    }
+
+    fn rule(source: &str, expression: &str) -> ExtractionRule {
+        serde_json::from_value(json!({
+            "source": source,
+            "expression": expression,
+            "variable": "out",
+            "scope": "environment",
+        }))
+        .unwrap()
+    }
+
+    fn extract(rule: ExtractionRule, body: &str) -> Result<String, String> {
+        rule.extract(&response(200, body))
+    }
+
+    #[test]
+    fn json_path_takes_the_first_match() {
+        let body =
+            r#"{"token": "t0k", "user": {"id": 7, "roles": ["admin", "dev"]}, "meta": {"a": 1}}"#;
+        assert_eq!(extract(rule("json_path", "$.token"), body).unwrap(), "t0k");
+        assert_eq!(extract(rule("json_path", "$.user.id"), body).unwrap(), "7");
+        assert_eq!(
+            extract(rule("json_path", "$.user.roles[*]"), body).unwrap(),
+            "admin"
+        );
+        assert_eq!(
+            extract(rule("json_path", "$.meta"), body).unwrap(),
+            r#"{"a":1}"#
+        );
+        assert_eq!(
+            extract(rule("json_path", "$.missing"), body).unwrap_err(),
+            "'$.missing' matched nothing"
+        );
+        assert_eq!(
+            extract(rule("json_path", "$.token"), "<xml/>").unwrap_err(),
+            "response body is not JSON"
+        );
+        assert!(extract(rule("json_path", "token"), body)
+            .unwrap_err()
+            .starts_with("invalid JSON path 'token'"));
+    }
+
+    #[test]
+    fn xpath_reads_text_attributes_and_functions() {
+        let body = r#"<order id="42"><item sku="a">Pen</item><item sku="b">Ink</item></order>"#;
+        assert_eq!(extract(rule("xpath", "/order/@id"), body).unwrap(), "42");
+        assert_eq!(
+            extract(rule("xpath", "/order/item[2]"), body).unwrap(),
+            "Ink"
+        );
+        assert_eq!(extract(rule("xpath", "count(//item)"), body).unwrap(), "2");
+        assert_eq!(
+            extract(rule("xpath", "/order/missing"), body).unwrap_err(),
+            "'/order/missing' matched nothing"
+        );
+        assert_eq!(
+            extract(rule("xpath", "/order"), "{}").unwrap_err(),
+            "response body is not XML"
+        );
+    }
+
+    #[test]
+    fn regex_takes_the_first_group_or_whole_match() {
+        let body = "id=42; csrf=abc-123";
+        assert_eq!(
+            extract(rule("regex", r"csrf=([\w-]+)"), body).unwrap(),
+            "abc-123"
+        );
+        assert_eq!(extract(rule("regex", r"\d+"), body).unwrap(), "42");
+        assert_eq!(
+            extract(rule("regex", "nope"), body).unwrap_err(),
+            "'nope' matched nothing"
+        );
+        assert!(extract(rule("regex", "("), body)
+            .unwrap_err()
+            .starts_with("invalid regex '('"));
+    }
+
+    #[test]
+    fn headers_cookies_and_status() {
+        assert_eq!(
+            extract(rule("header", "x-request-id"), "").unwrap(),
+            "req-1"
+        );
+        assert_eq!(extract(rule("cookie", "session"), "").unwrap(), "abc123");
+        assert_eq!(extract(rule("status", ""), "").unwrap(), "200");
+        assert_eq!(
+            extract(rule("cookie", "other"), "").unwrap_err(),
+            "no 'other' cookie in the response"
+        );
+    }
+
+    #[test]
+    fn extract_values_skips_failed_responses_and_missing_scopes() {
+        let rules = [rule("status", "")];
+        let results = extract_values(&rules, &response(500, ""), true, true);
+        assert_eq!(
+            results[0].error.as_deref(),
+            Some("skipped, response status was 500")
+        );
+        assert!(results[0].update().is_none());
+
+        let results = extract_values(&rules, &response(200, ""), true, false);
+        assert_eq!(results[0].error.as_deref(), Some("no environment selected"));
+
+        let results = extract_values(&rules, &response(204, ""), false, true);
+        let update = results[0].update().unwrap();
+        assert_eq!(update.key, "out");
+        assert_eq!(update.scope, VariableScope::Environment);
+        assert_eq!(update.value.as_deref(), Some("204"));
+    }
+
+    #[test]
+    fn validates_saved_rules() {
+        let valid = json!([
+            {"source": "json_path", "expression": "$.token", "variable": "token", "scope": "collection"},
+            {"source": "xpath", "expression": "//id", "variable": "id", "scope": "global"},
+            {"source": "status", "variable": "code", "scope": "environment"},
+        ]);
+        assert!(validate_extractions(&valid.to_string()).is_ok());
+
+        let check = |rule: serde_json::Value| validate_extractions(&json!([rule]).to_string());
+        assert_eq!(
+            check(
+                json!({"source": "regex", "expression": " ", "variable": "x", "scope": "global"})
+            )
+            .unwrap_err(),
+            "Extraction rule for 'x' has no expression"
+        );
+        assert_eq!(
+            check(json!({"source": "status", "variable": "x", "scope": "data"})).unwrap_err(),
+            "Extraction rule for 'x' cannot save to iteration data"
+        );
+        assert!(check(
+            json!({"source": "xpath", "expression": "//[", "variable": "x", "scope": "global"})
+        )
+        .unwrap_err()
+        .starts_with("Invalid XPath '//['"));
+    }
+}
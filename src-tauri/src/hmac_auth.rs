@@ -0,0 +1,538 @@
+use crate::http_client::AuthData;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+
+const DEFAULT_COMPONENTS: [&str; 4] = ["method", "path", "timestamp", "body_hash"];
+// Sends a signed timestamp when no header is named for it
+const DEFAULT_TIMESTAMP_HEADER: &str = "X-Timestamp";
+
+// Everything about the outgoing request that a signature may cover
+pub struct SigningInput<'a> {
+    pub method: &'a str,
+    pub url: &'a reqwest::Url,
+    pub body: &'a [u8],
+    pub content_type: Option<&'a str>,
+}
+
+// Computes the headers an "hmac" auth_type adds to the request
+pub fn sign_request(
+    auth: &AuthData,
+    input: &SigningInput,
+) -> Result<Vec<(String, String)>, String> {
+    let secret = auth
+        .secret
+        .as_deref()
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| "HMAC auth requires a secret".to_string())?;
+    let algorithm = auth.algorithm.as_deref().unwrap_or("sha256").to_lowercase();
+    let timestamp = chrono::Utc::now().timestamp().to_string();
+    let nonce = uuid::Uuid::new_v4().simple().to_string()[..12].to_string();
+
+    match auth.preset.as_deref().unwrap_or("custom") {
+        "hawk" => sign_hawk(auth, secret, &algorithm, input, &timestamp, &nonce),
+        "custom" => sign_custom(auth, secret, &algorithm, input, &timestamp, &nonce),
+        other => Err(format!("Unknown HMAC preset: {}", other)),
+    }
+}
+
+fn sign_custom(
+    auth: &AuthData,
+    secret: &str,
+    algorithm: &str,
+    input: &SigningInput,
+    timestamp: &str,
+    nonce: &str,
+) -> Result<Vec<(String, String)>, String> {
+    let encoding = auth.signature_encoding.as_deref().unwrap_or("base64");
+    let components: Vec<String> = match &auth.signed_components {
+        Some(components) if !components.is_empty() => components.clone(),
+        _ => DEFAULT_COMPONENTS.iter().map(|c| c.to_string()).collect(),
+    };
+
+    let mut parts = Vec::with_capacity(components.len());
+    for component in &components {
+        let part = match component.as_str() {
+            "method" => input.method.to_uppercase(),
+            "path" => input.url.path().to_string(),
+            "query" => input.url.query().unwrap_or("").to_string(),
+            "host" => host_with_port(input.url),
+            "timestamp" => timestamp.to_string(),
+            "nonce" => nonce.to_string(),
+            "content_type" => input.content_type.unwrap_or("").to_string(),
+            "body_hash" => encode(&hash_digest(algorithm, input.body)?, encoding)?,
+            other => return Err(format!("Unknown HMAC signed component: {}", other)),
+        };
+        parts.push(part);
+    }
+    let string_to_sign = parts.join("\n");
+
+    let signature = encode(
+        &hmac_digest(algorithm, secret.as_bytes(), string_to_sign.as_bytes())?,
+        encoding,
+    )?;
+
+    let header_value = auth
+        .header_template
+        .as_deref()
+        .filter(|t| !t.is_empty())
+        .unwrap_or("{signature}")
+        .replace("{signature}", &signature)
+        .replace("{key_id}", auth.key_id.as_deref().unwrap_or(""))
+        .replace("{timestamp}", timestamp)
+        .replace("{nonce}", nonce)
+        .replace("{algorithm}", algorithm);
+
+    let mut headers = vec![(header_name(auth), header_value)];
+    let timestamp_header = auth
+        .timestamp_header
+        .as_deref()
+        .filter(|h| !h.is_empty())
+        .or_else(|| {
+            components
+                .iter()
+                .any(|c| c == "timestamp")
+                .then_some(DEFAULT_TIMESTAMP_HEADER)
+        });
+    if let Some(timestamp_header) = timestamp_header {
+        headers.push((timestamp_header.to_string(), timestamp.to_string()));
+    }
+    Ok(headers)
+}
+
+// Hawk (https://github.com/mozilla/hawk) header authentication, version 1
+fn sign_hawk(
+    auth: &AuthData,
+    secret: &str,
+    algorithm: &str,
+    input: &SigningInput,
+    timestamp: &str,
+    nonce: &str,
+) -> Result<Vec<(String, String)>, String> {
+    if algorithm != "sha256" && algorithm != "sha1" {
+        return Err(format!("Hawk supports sha256 and sha1, not {}", algorithm));
+    }
+    let id = auth
+        .key_id
+        .as_deref()
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| "Hawk auth requires a key id".to_string())?;
+    // Header attributes are printable ASCII, with quotes and backslashes
+    // escaped; the signed string escapes backslashes only
+    let ext = auth.ext.as_deref().unwrap_or("");
+    if !ext.chars().all(|c| c == ' ' || c.is_ascii_graphic()) {
+        return Err("Hawk ext may only contain printable ASCII characters".to_string());
+    }
+
+    let payload_hash = if input.body.is_empty() {
+        String::new()
+    } else {
+        let mime = input
+            .content_type
+            .and_then(|ct| ct.split(';').next())
+            .unwrap_or("")
+            .trim()
+            .to_lowercase();
+        let mut payload = format!("hawk.1.payload\n{}\n", mime).into_bytes();
+        payload.extend_from_slice(input.body);
+        payload.push(b'\n');
+        BASE64.encode(hash_digest(algorithm, &payload)?)
+    };
+
+    let resource = match input.url.query() {
+        Some(query) => format!("{}?{}", input.url.path(), query),
+        None => input.url.path().to_string(),
+    };
+    let host = input.url.host_str().unwrap_or("").to_lowercase();
+    let port = input.url.port_or_known_default().unwrap_or(80);
+
+    let normalized = format!(
+        "hawk.1.header\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n",
+        timestamp,
+        nonce,
+        input.method.to_uppercase(),
+        resource,
+        host,
+        port,
+        payload_hash,
+        ext.replace('\\', "\\\\")
+    );
+    let mac = BASE64.encode(hmac_digest(
+        algorithm,
+        secret.as_bytes(),
+        normalized.as_bytes(),
+    )?);
+
+    let mut header = format!(
+        "Hawk id=\"{}\", ts=\"{}\", nonce=\"{}\"",
+        id, timestamp, nonce
+    );
+    if !payload_hash.is_empty() {
+        header.push_str(&format!(", hash=\"{}\"", payload_hash));
+    }
+    if !ext.is_empty() {
+        let escaped = ext.replace('\\', "\\\\").replace('"', "\\\"");
+        header.push_str(&format!(", ext=\"{}\"", escaped));
+    }
+    header.push_str(&format!(", mac=\"{}\"", mac));
+
+    Ok(vec![(header_name(auth), header)])
+}
+
+fn header_name(auth: &AuthData) -> String {
+    auth.header_name
+        .as_deref()
+        .filter(|h| !h.is_empty())
+        .unwrap_or("Authorization")
+        .to_string()
+}
+
+fn host_with_port(url: &reqwest::Url) -> String {
+    let host = url.host_str().unwrap_or("");
+    match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    }
+}
+
+fn hmac_digest(algorithm: &str, key: &[u8], data: &[u8]) -> Result<Vec<u8>, String> {
+    match algorithm {
+        "sha1" => Ok(mac::<Hmac<Sha1>>(key, data)),
+        "sha256" => Ok(mac::<Hmac<Sha256>>(key, data)),
+        "sha512" => Ok(mac::<Hmac<Sha512>>(key, data)),
+        other => Err(format!("Unsupported HMAC algorithm: {}", other)),
+    }
+}
+
+fn mac<M: Mac + hmac::digest::KeyInit>(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac =
+        <M as hmac::digest::KeyInit>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hash_digest(algorithm: &str, data: &[u8]) -> Result<Vec<u8>, String> {
+    match algorithm {
+        "sha1" => Ok(Sha1::digest(data).to_vec()),
+        "sha256" => Ok(Sha256::digest(data).to_vec()),
+        "sha512" => Ok(Sha512::digest(data).to_vec()),
+        other => Err(format!("Unsupported hash algorithm: {}", other)),
+    }
+}
+
+fn encode(bytes: &[u8], encoding: &str) -> Result<String, String> {
+    match encoding {
+        "base64" => Ok(BASE64.encode(bytes)),
+        "hex" => Ok(hex::encode(bytes)),
+        other => Err(format!("Unsupported signature encoding: {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input<'a>(method: &'a str, url: &'a reqwest::Url, body: &'a [u8]) -> SigningInput<'a> {
+        SigningInput {
+            method,
+            url,
+            body,
+            content_type: Some("text/plain"),
+        }
+    }
+
+    fn hawk_auth() -> AuthData {
+        AuthData {
+            preset: Some("hawk".to_string()),
+            key_id: Some("dh37fgj492je".to_string()),
+            secret: Some("werxhqb98rpaxn39848xrunpaw3489ruxnpa98w4rxn".to_string()),
+            ext: Some("some-app-ext-data".to_string()),
+            ..Default::default()
+        }
+    }
+
+    // Vectors from the Hawk README
+    #[test]
+    fn hawk_header_without_payload() {
+        let auth = hawk_auth();
+        let url = reqwest::Url::parse("http://example.com:8000/resource/1?b=1&a=2").unwrap();
+        let headers = sign_hawk(
+            &auth,
+            "werxhqb98rpaxn39848xrunpaw3489ruxnpa98w4rxn",
+            "sha256",
+            &input("GET", &url, b""),
+            "1353832234",
+            "j4h3g2",
+        )
+        .unwrap();
+
+        assert_eq!(
+            headers,
+            vec![(
+                "Authorization".to_string(),
+                "Hawk id=\"dh37fgj492je\", ts=\"1353832234\", nonce=\"j4h3g2\", ext=\"some-app-ext-data\", mac=\"6R4rV5iE+NPoym+WwjeHzjAGXUtLNIxmo1vpMofpLAE=\"".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn hawk_header_with_payload() {
+        let auth = hawk_auth();
+        let url = reqwest::Url::parse("http://example.com:8000/resource/1?b=1&a=2").unwrap();
+        let headers = sign_hawk(
+            &auth,
+            "werxhqb98rpaxn39848xrunpaw3489ruxnpa98w4rxn",
+            "sha256",
+            &input("POST", &url, b"Thank you for flying Hawk"),
+            "1353832234",
+            "j4h3g2",
+        )
+        .unwrap();
+
+        assert_eq!(
+            headers[0].1,
+            "Hawk id=\"dh37fgj492je\", ts=\"1353832234\", nonce=\"j4h3g2\", hash=\"Yi9LfIIFRtBEPt74PVmbTF/xVAwPn7ub15ePICfgnuY=\", ext=\"some-app-ext-data\", mac=\"aSe1DERmZuRl3pI36/9BdZmnErTw3sNzOOAUlfeKjVw=\""
+        );
+    }
+
+    #[test]
+    fn hawk_escapes_ext() {
+        let auth = AuthData {
+            ext: Some(r#"say "hi" \ bye"#.to_string()),
+            ..hawk_auth()
+        };
+        let url = reqwest::Url::parse("http://example.com:8000/resource/1").unwrap();
+        let headers = sign_hawk(
+            &auth,
+            "secret",
+            "sha256",
+            &input("GET", &url, b""),
+            "1",
+            "n",
+        )
+        .unwrap();
+
+        let normalized =
+            "hawk.1.header\n1\nn\nGET\n/resource/1\nexample.com\n8000\n\nsay \"hi\" \\\\ bye\n";
+        let mac = BASE64.encode(hmac_digest("sha256", b"secret", normalized.as_bytes()).unwrap());
+        assert_eq!(
+            headers[0].1,
+            format!(
+                r#"Hawk id="dh37fgj492je", ts="1", nonce="n", ext="say \"hi\" \\ bye", mac="{}""#,
+                mac
+            )
+        );
+    }
+
+    #[test]
+    fn hawk_rejects_unprintable_ext() {
+        let url = reqwest::Url::parse("http://example.com/").unwrap();
+        for ext in ["line\nbreak", "caf\u{e9}"] {
+            let auth = AuthData {
+                ext: Some(ext.to_string()),
+                ..hawk_auth()
+            };
+            let result = sign_hawk(
+                &auth,
+                "secret",
+                "sha256",
+                &input("GET", &url, b""),
+                "1",
+                "n",
+            );
+            assert_eq!(
+                result.unwrap_err(),
+                "Hawk ext may only contain printable ASCII characters"
+            );
+        }
+    }
+
+    #[test]
+    fn hawk_rejects_sha512() {
+        let url = reqwest::Url::parse("http://example.com/").unwrap();
+        let result = sign_hawk(
+            &hawk_auth(),
+            "secret",
+            "sha512",
+            &input("GET", &url, b""),
+            "1",
+            "n",
+        );
+        assert!(result.is_err());
+    }
+
+    // RFC 4231 test case 2
+    #[test]
+    fn hmac_sha256_known_vector() {
+        let mac = hmac_digest("sha256", b"Jefe", b"what do ya want for nothing?").unwrap();
+        assert_eq!(
+            hex::encode(mac),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn custom_signs_components_in_the_given_order() {
+        let auth = AuthData {
+            signed_components: Some(vec![
+                "timestamp".to_string(),
+                "query".to_string(),
+                "method".to_string(),
+                "host".to_string(),
+                "path".to_string(),
+            ]),
+            signature_encoding: Some("hex".to_string()),
+            ..Default::default()
+        };
+        let url = reqwest::Url::parse("https://api.example.com:8443/v1/items?page=2").unwrap();
+        let headers = sign_custom(
+            &auth,
+            "secret",
+            "sha256",
+            &input("post", &url, b"{}"),
+            "1700000000",
+            "abc",
+        )
+        .unwrap();
+
+        let expected = hmac_digest(
+            "sha256",
+            b"secret",
+            b"1700000000\npage=2\nPOST\napi.example.com:8443\n/v1/items",
+        )
+        .unwrap();
+        assert_eq!(
+            headers,
+            vec![
+                ("Authorization".to_string(), hex::encode(expected)),
+                ("X-Timestamp".to_string(), "1700000000".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn custom_default_components_hash_the_body() {
+        let auth = AuthData::default();
+        let url = reqwest::Url::parse("https://api.example.com/v1/items").unwrap();
+        let headers = sign_custom(
+            &auth,
+            "secret",
+            "sha256",
+            &input("GET", &url, b""),
+            "1700000000",
+            "abc",
+        )
+        .unwrap();
+
+        // SHA-256 of an empty body
+        let expected = hmac_digest(
+            "sha256",
+            b"secret",
+            b"GET\n/v1/items\n1700000000\n47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=",
+        )
+        .unwrap();
+        assert_eq!(headers[0].1, BASE64.encode(expected));
+        // The server needs the signed timestamp to check the signature
+        assert_eq!(
+            headers[1],
+            ("X-Timestamp".to_string(), "1700000000".to_string())
+        );
+    }
+
+    #[test]
+    fn custom_sends_the_timestamp_only_when_signed_or_named() {
+        let url = reqwest::Url::parse("https://api.example.com/").unwrap();
+        let sign = |auth: &AuthData| {
+            sign_custom(
+                auth,
+                "secret",
+                "sha256",
+                &input("GET", &url, b""),
+                "1700000000",
+                "abc",
+            )
+            .unwrap()
+        };
+
+        let unsigned = AuthData {
+            signed_components: Some(vec!["method".to_string(), "path".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(sign(&unsigned).len(), 1);
+
+        let named = AuthData {
+            timestamp_header: Some("X-Date".to_string()),
+            ..unsigned
+        };
+        assert_eq!(
+            sign(&named)[1],
+            ("X-Date".to_string(), "1700000000".to_string())
+        );
+    }
+
+    #[test]
+    fn custom_fills_the_header_template() {
+        let auth = AuthData {
+            key_id: Some("client-1".to_string()),
+            header_name: Some("X-Signature".to_string()),
+            header_template: Some(
+                "HMAC-{algorithm} key={key_id}, ts={timestamp}, nonce={nonce}, sig={signature}"
+                    .to_string(),
+            ),
+            signed_components: Some(vec!["method".to_string()]),
+            timestamp_header: Some("X-Timestamp".to_string()),
+            ..Default::default()
+        };
+        let url = reqwest::Url::parse("https://api.example.com/").unwrap();
+        let headers = sign_custom(
+            &auth,
+            "secret",
+            "sha1",
+            &input("GET", &url, b""),
+            "1700000000",
+            "abc",
+        )
+        .unwrap();
+
+        let signature = BASE64.encode(hmac_digest("sha1", b"secret", b"GET").unwrap());
+        assert_eq!(
+            headers,
+            vec![
+                (
+                    "X-Signature".to_string(),
+                    format!(
+                        "HMAC-sha1 key=client-1, ts=1700000000, nonce=abc, sig={}",
+                        signature
+                    )
+                ),
+                ("X-Timestamp".to_string(), "1700000000".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn custom_rejects_unknown_components() {
+        let auth = AuthData {
+            signed_components: Some(vec!["cookie".to_string()]),
+            ..Default::default()
+        };
+        let url = reqwest::Url::parse("https://api.example.com/").unwrap();
+        let result = sign_custom(
+            &auth,
+            "secret",
+            "sha256",
+            &input("GET", &url, b""),
+            "1",
+            "n",
+        );
+        assert_eq!(result.unwrap_err(), "Unknown HMAC signed component: cookie");
+    }
+
+    #[test]
+    fn sign_request_requires_a_secret() {
+        let url = reqwest::Url::parse("https://api.example.com/").unwrap();
+        let result = sign_request(&AuthData::default(), &input("GET", &url, b""));
+        assert_eq!(result.unwrap_err(), "HMAC auth requires a secret");
+    }
+}
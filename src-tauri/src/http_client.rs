@@ -1,9 +1,15 @@
+use crate::hmac_auth::{sign_request, SigningInput};
+use crate::assertions::{Assertion, AssertionResult};
+use crate::extraction::{ExtractionResult, ExtractionRule};
+use crate::interpolation::VariableIssue;
+use crate::json_schema::{ResponseSchema, SchemaValidation};
+use crate::scripting::{ScriptLog, TestResult};
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::time::Instant;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct HttpRequest {
     pub method: String,
     pub url: String,
@@ -13,16 +19,44 @@ pub struct HttpRequest {
     pub auth_type: String,
     pub auth_data: AuthData,
     pub form_data: Option<Vec<FormDataField>>,
+    // Saved location of the request, used to resolve folder-level settings
+    pub collection_id: Option<String>,
+    // Refuse to send while any {{variable}} is unresolved or malformed
+    #[serde(default)]
+    pub strict_variables: bool,
+    // Request-local variables, highest precedence when resolving {{name}}
+    #[serde(default)]
+    pub variables: Vec<KeyValue>,
+    // Environments to resolve against, later ones overriding earlier ones;
+    // the globally active environment when absent
+    #[serde(default)]
+    pub environment_ids: Option<Vec<String>>,
+    #[serde(default)]
+    pub pre_request_script: String,
+    #[serde(default)]
+    pub test_script: String,
+    #[serde(default)]
+    pub assertions: Vec<Assertion>,
+    #[serde(default)]
+    pub extractions: Vec<ExtractionRule>,
+    #[serde(default)]
+    pub response_schema: Option<ResponseSchema>,
+    // The collection runner's current data row, resolved below request-local variables
+    #[serde(default)]
+    pub iteration_data: Vec<KeyValue>,
+    // Saved request's name, for pm.info.requestName; empty for unsaved requests
+    #[serde(default)]
+    pub name: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct KeyValue {
     pub key: String,
     pub value: String,
     pub enabled: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FormDataField {
     pub key: String,
     pub value: String,
@@ -32,13 +66,24 @@ pub struct FormDataField {
     pub file_path: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct AuthData {
     pub username: Option<String>,
     pub password: Option<String>,
     pub token: Option<String>,
     pub key: Option<String>,
     pub value_field: Option<String>,
+    // HMAC signing ("hmac" auth_type)
+    pub secret: Option<String>,
+    pub key_id: Option<String>,
+    pub algorithm: Option<String>,
+    pub preset: Option<String>,
+    pub header_name: Option<String>,
+    pub header_template: Option<String>,
+    pub signed_components: Option<Vec<String>>,
+    pub signature_encoding: Option<String>,
+    pub timestamp_header: Option<String>,
+    pub ext: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -50,6 +95,20 @@ pub struct HttpResponse {
     pub response_time: u128,
     pub size: usize,
     pub cookies: Vec<Cookie>,
+    // The request as sent, after variables were resolved
+    pub resolved_request: Option<HttpRequest>,
+    #[serde(default)]
+    pub variable_issues: Vec<VariableIssue>,
+    #[serde(default)]
+    pub script_logs: Vec<ScriptLog>,
+    #[serde(default)]
+    pub test_results: Vec<TestResult>,
+    #[serde(default)]
+    pub assertion_results: Vec<AssertionResult>,
+    #[serde(default)]
+    pub extractions: Vec<ExtractionResult>,
+    #[serde(default)]
+    pub schema_validation: Option<SchemaValidation>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -105,6 +164,33 @@ pub async fn send_request(request: HttpRequest) -> Result<HttpResponse, String>
                 req_builder = req_builder.header(key.as_str(), value.as_str());
             }
         }
+        "hmac" => {
+            let url = reqwest::Url::parse(&request.url).map_err(|e| e.to_string())?;
+            let body: &[u8] = match request.body_type.as_str() {
+                "json" | "raw" => request.body.as_bytes(),
+                _ => &[],
+            };
+            let content_type = request
+                .headers
+                .iter()
+                .filter(|h| h.enabled)
+                .find(|h| h.key.eq_ignore_ascii_case("content-type"))
+                .map(|h| h.value.as_str())
+                .or((request.body_type == "json").then_some("application/json"));
+
+            let signed_headers = sign_request(
+                &request.auth_data,
+                &SigningInput {
+                    method: &request.method,
+                    url: &url,
+                    body,
+                    content_type,
+                },
+            )?;
+            for (name, value) in signed_headers {
+                req_builder = req_builder.header(name, value);
+            }
+        }
         _ => {}
     }
 
@@ -211,5 +297,12 @@ pub async fn send_request(request: HttpRequest) -> Result<HttpResponse, String>
         response_time: elapsed,
         size,
         cookies,
+        resolved_request: None,
+        variable_issues: Vec::new(),
+        script_logs: Vec::new(),
+        test_results: Vec::new(),
+        assertion_results: Vec::new(),
+        extractions: Vec::new(),
+        schema_validation: None,
     })
 }
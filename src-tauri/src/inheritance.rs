@@ -0,0 +1,57 @@
+use crate::db::Collection;
+use crate::http_client::{AuthData, HttpRequest, KeyValue};
+
+// Resolves auth_type "inherit" against the request's folder chain (nearest first)
+pub fn apply_inherited_auth(request: &mut HttpRequest, chain: &[Collection]) -> Result<(), String> {
+    if request.auth_type != "inherit" {
+        return Ok(());
+    }
+
+    match chain.iter().find(|c| c.auth_type != "inherit") {
+        Some(collection) => {
+            request.auth_type = collection.auth_type.clone();
+            request.auth_data = serde_json::from_str(&collection.auth_data)
+                .map_err(|e| format!("Invalid auth settings on '{}': {}", collection.name, e))?;
+        }
+        None => {
+            request.auth_type = "none".to_string();
+            request.auth_data = AuthData::default();
+        }
+    }
+
+    Ok(())
+}
+
+// Merges folder default headers into the request; nearer folders override
+// their ancestors and the request's own headers always win
+pub fn apply_default_headers(
+    request: &mut HttpRequest,
+    chain: &[Collection],
+) -> Result<(), String> {
+    let mut defaults: Vec<KeyValue> = Vec::new();
+
+    for collection in chain.iter().rev() {
+        let headers: Vec<KeyValue> = serde_json::from_str(&collection.headers)
+            .map_err(|e| format!("Invalid default headers on '{}': {}", collection.name, e))?;
+        for header in headers
+            .into_iter()
+            .filter(|h| h.enabled && !h.key.is_empty())
+        {
+            defaults.retain(|d| !d.key.eq_ignore_ascii_case(&header.key));
+            defaults.push(header);
+        }
+    }
+
+    defaults.retain(|d| {
+        !request
+            .headers
+            .iter()
+            .any(|h| h.enabled && h.key.eq_ignore_ascii_case(&d.key))
+    });
+    if !defaults.is_empty() {
+        defaults.append(&mut request.headers);
+        request.headers = defaults;
+    }
+
+    Ok(())
+}
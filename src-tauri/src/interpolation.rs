@@ -0,0 +1,512 @@
+use crate::dynamic;
+use crate::http_client::{AuthData, HttpRequest};
+use crate::json_schema::ResponseSchema;
+use crate::template::{self, Arg, Operand};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    Unresolved,
+    Malformed,
+    Invalid,
+    Cycle,
+    DepthExceeded,
+}
+
+// How many levels of variables referencing variables are followed
+pub const MAX_DEPTH: usize = 10;
+
+// A placeholder that could not be substituted, and where it was found
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VariableIssue {
+    pub kind: IssueKind,
+    pub name: String,
+    pub location: String,
+    pub position: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl std::fmt::Display for VariableIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            IssueKind::Unresolved => write!(f, "{{{{{}}}}} in {}", self.name, self.location),
+            IssueKind::Malformed => write!(
+                f,
+                "malformed placeholder '{}' in {} at {}",
+                self.name, self.location, self.position
+            ),
+            IssueKind::Invalid => write!(
+                f,
+                "{} in {}: {}",
+                self.name,
+                self.location,
+                self.message.as_deref().unwrap_or("invalid expression")
+            ),
+            IssueKind::Cycle => write!(
+                f,
+                "circular variable reference {} in {}",
+                self.name, self.location
+            ),
+            IssueKind::DepthExceeded => write!(
+                f,
+                "variable references nested more than {} levels deep ({}) in {}",
+                MAX_DEPTH, self.name, self.location
+            ),
+        }
+    }
+}
+
+impl VariableIssue {
+    // Cycles and runaway nesting are errors even outside strict mode
+    pub fn is_fatal(&self) -> bool {
+        matches!(self.kind, IssueKind::Cycle | IssueKind::DepthExceeded)
+    }
+}
+
+// Replaces {{name}} placeholders with variable values, following references
+// inside values and recording unresolved and malformed placeholders
+pub fn interpolate_with_issues(
+    text: &str,
+    variables: &HashMap<String, String>,
+    location: &str,
+    issues: &mut Vec<VariableIssue>,
+) -> String {
+    let mut resolver = Resolver {
+        variables,
+        location,
+        issues,
+        stack: Vec::new(),
+    };
+    resolver.text(text, None)
+}
+
+struct Resolver<'a> {
+    variables: &'a HashMap<String, String>,
+    location: &'a str,
+    issues: &'a mut Vec<VariableIssue>,
+    // Names being expanded, outermost first
+    stack: Vec<String>,
+}
+
+impl Resolver<'_> {
+    // Nested issues are reported at the position of the top-level placeholder
+    fn text(&mut self, text: &str, origin: Option<usize>) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut offset = 0;
+
+        while let Some(found) = text[offset..].find("{{") {
+            let start = offset + found;
+            let position = origin.unwrap_or(start);
+            result.push_str(&text[offset..start]);
+            let after_open = &text[start + 2..];
+
+            let Some(end) = after_open.find("}}") else {
+                self.issue(IssueKind::Malformed, truncate(&text[start..], 32), position);
+                result.push_str(&text[start..]);
+                return result;
+            };
+
+            let placeholder = &text[start..start + 2 + end + 2];
+            let name = after_open[..end].trim();
+
+            if name.is_empty() || name.contains('{') {
+                self.issue(IssueKind::Malformed, placeholder.to_string(), position);
+                result.push_str(placeholder);
+            } else {
+                let value = self.variable(name, placeholder, position);
+                result.push_str(&value);
+            }
+
+            offset = start + placeholder.len();
+        }
+
+        result.push_str(&text[offset..]);
+        result
+    }
+
+    fn variable(&mut self, name: &str, placeholder: &str, position: usize) -> String {
+        if !self.variables.contains_key(name) && template::is_expression(name) {
+            return self.expression(name, placeholder, position);
+        }
+        match self.value(name, position) {
+            Ok(Some(value)) => value,
+            Ok(None) => {
+                self.issue(IssueKind::Unresolved, name.to_string(), position);
+                placeholder.to_string()
+            }
+            Err(e) => {
+                self.invalid(placeholder, e, position);
+                placeholder.to_string()
+            }
+        }
+    }
+
+    // Looks up a scoped or built-in variable; None when it is not defined
+    fn value(&mut self, name: &str, position: usize) -> Result<Option<String>, String> {
+        let variables = self.variables;
+        if let Some(value) = variables.get(name) {
+            let placeholder = format!("{{{{{}}}}}", name);
+            return Ok(Some(self.expand(name, value, &placeholder, position)));
+        }
+
+        // Scoped variables may shadow the built-in $ names
+        match name.strip_prefix('$') {
+            Some(expression) => dynamic::evaluate(expression, &mut |key| {
+                variables
+                    .get(key)
+                    .map(|value| self.expand(key, value, &format!("{{{{{}}}}}", key), position))
+            })
+            .transpose(),
+            None => Ok(None),
+        }
+    }
+
+    // Evaluates `operand | filter(args) | ...`
+    fn expression(&mut self, text: &str, placeholder: &str, position: usize) -> String {
+        let expression = match template::parse(text) {
+            Ok(expression) => expression,
+            Err(e) => {
+                self.invalid(placeholder, e, position);
+                return placeholder.to_string();
+            }
+        };
+
+        let evaluated = (|| {
+            let mut value = match &expression.operand {
+                Operand::Literal(literal) => Some(literal.clone()),
+                Operand::Variable(name) => self.value(name, position)?,
+                Operand::Call(name, args) => {
+                    let args = self.args(args, position)?;
+                    Some(template::call(name, &args)?)
+                }
+            };
+
+            for filter in &expression.filters {
+                let args = self.args(&filter.args, position)?;
+                value = match value {
+                    Some(input) => Some(template::apply_filter(&filter.name, &input, &args)?),
+                    // `default` also covers variables that are not defined
+                    None if filter.name == "default" => args.first().cloned(),
+                    None => None,
+                };
+            }
+            Ok::<_, String>(value)
+        })();
+
+        match evaluated {
+            Ok(Some(value)) => value,
+            Ok(None) => {
+                let name = match &expression.operand {
+                    Operand::Variable(name) => name.clone(),
+                    _ => text.to_string(),
+                };
+                self.issue(IssueKind::Unresolved, name, position);
+                placeholder.to_string()
+            }
+            Err(e) => {
+                self.invalid(placeholder, e, position);
+                placeholder.to_string()
+            }
+        }
+    }
+
+    fn args(&mut self, args: &[Arg], position: usize) -> Result<Vec<String>, String> {
+        args.iter()
+            .map(|arg| match arg {
+                Arg::Literal(literal) => Ok(literal.clone()),
+                Arg::Variable(name) => self
+                    .value(name, position)?
+                    .ok_or_else(|| format!("unknown variable '{}'", name)),
+            })
+            .collect()
+    }
+
+    // Resolves the placeholders inside a variable's own value
+    fn expand(&mut self, name: &str, value: &str, placeholder: &str, position: usize) -> String {
+        if !value.contains("{{") {
+            return value.to_string();
+        }
+        if self.stack.iter().any(|n| n == name) {
+            let mut path = self.stack.clone();
+            path.push(name.to_string());
+            self.issue(IssueKind::Cycle, path.join(" -> "), position);
+            return placeholder.to_string();
+        }
+        if self.stack.len() >= MAX_DEPTH {
+            self.issue(IssueKind::DepthExceeded, self.stack.join(" -> "), position);
+            return placeholder.to_string();
+        }
+
+        self.stack.push(name.to_string());
+        let expanded = self.text(value, Some(position));
+        self.stack.pop();
+        expanded
+    }
+
+    fn issue(&mut self, kind: IssueKind, name: String, position: usize) {
+        self.issues.push(VariableIssue {
+            kind,
+            name,
+            location: self.location.to_string(),
+            position,
+            message: None,
+        });
+    }
+
+    fn invalid(&mut self, placeholder: &str, message: String, position: usize) {
+        self.issues.push(VariableIssue {
+            kind: IssueKind::Invalid,
+            name: placeholder.to_string(),
+            location: self.location.to_string(),
+            position,
+            message: Some(message),
+        });
+    }
+}
+
+// Resolves placeholders in every user-editable part of the request and
+// returns the ones that could not be resolved
+pub fn interpolate_request(
+    request: &mut HttpRequest,
+    variables: &HashMap<String, String>,
+) -> Vec<VariableIssue> {
+    let mut issues = Vec::new();
+    let mut resolve = |text: &mut String, location: &str| {
+        *text = interpolate_with_issues(text, variables, location, &mut issues);
+    };
+
+    resolve(&mut request.url, "url");
+    resolve(&mut request.body, "body");
+
+    for header in request.headers.iter_mut().filter(|h| h.enabled) {
+        let location = format!("headers[{}]", header.key);
+        resolve(&mut header.key, &location);
+        resolve(&mut header.value, &location);
+    }
+
+    if let Some(fields) = request.form_data.as_mut() {
+        for field in fields.iter_mut().filter(|f| f.enabled) {
+            let location = format!("form_data[{}]", field.key);
+            resolve(&mut field.key, &location);
+            resolve(&mut field.value, &location);
+            if let Some(path) = field.file_path.as_mut() {
+                resolve(path, &location);
+            }
+        }
+    }
+
+    for (i, assertion) in request.assertions.iter_mut().enumerate() {
+        let location = format!("assertions[{}]", i);
+        resolve(&mut assertion.property, &location);
+        resolve(&mut assertion.value, &location);
+    }
+
+    if let Some(ResponseSchema::File { path }) = request.response_schema.as_mut() {
+        resolve(path, "response_schema");
+    }
+
+    for (name, field) in auth_fields(&mut request.auth_data) {
+        resolve(field, &format!("auth_data.{}", name));
+    }
+    if let Some(components) = request.auth_data.signed_components.as_mut() {
+        for component in components.iter_mut() {
+            resolve(component, "auth_data.signed_components");
+        }
+    }
+
+    issues
+}
+
+// Error message used when strict mode refuses to send
+pub fn describe_issues(issues: &[VariableIssue]) -> String {
+    let details: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+    format!("Unresolved variables: {}", details.join(", "))
+}
+
+fn auth_fields(auth: &mut AuthData) -> impl Iterator<Item = (&'static str, &mut String)> {
+    [
+        ("username", &mut auth.username),
+        ("password", &mut auth.password),
+        ("token", &mut auth.token),
+        ("key", &mut auth.key),
+        ("value_field", &mut auth.value_field),
+        ("secret", &mut auth.secret),
+        ("key_id", &mut auth.key_id),
+        ("algorithm", &mut auth.algorithm),
+        ("preset", &mut auth.preset),
+        ("header_name", &mut auth.header_name),
+        ("header_template", &mut auth.header_template),
+        ("signature_encoding", &mut auth.signature_encoding),
+        ("timestamp_header", &mut auth.timestamp_header),
+        ("ext", &mut auth.ext),
+    ]
+    .into_iter()
+    .filter_map(|(name, field)| field.as_mut().map(|f| (name, f)))
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((index, _)) => format!("{}...", &text[..index]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn resolve(text: &str, variables: &HashMap<String, String>) -> (String, Vec<VariableIssue>) {
+        let mut issues = Vec::new();
+        let result = interpolate_with_issues(text, variables, "url", &mut issues);
+        (result, issues)
+    }
+
+    #[test]
+    fn substitutes_variables() {
+        let variables = vars(&[("host", "api.example.com"), ("id", "42")]);
+        let (result, issues) = resolve("https://{{host}}/users/{{ id }}", &variables);
+        assert_eq!(result, "https://api.example.com/users/42");
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn follows_references_inside_values() {
+        let variables = vars(&[
+            ("base", "https://{{host}}/v{{version}}"),
+            ("host", "example.com"),
+            ("version", "2"),
+        ]);
+        let (result, issues) = resolve("{{base}}/users", &variables);
+        assert_eq!(result, "https://example.com/v2/users");
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn reports_unresolved_variables_and_keeps_them() {
+        let (result, issues) = resolve("/users/{{id}}?q={{query}}", &vars(&[("query", "x")]));
+        assert_eq!(result, "/users/{{id}}?q=x");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, IssueKind::Unresolved);
+        assert_eq!(issues[0].name, "id");
+        assert_eq!(issues[0].position, 7);
+        assert!(!issues[0].is_fatal());
+        assert_eq!(issues[0].to_string(), "{{id}} in url");
+    }
+
+    #[test]
+    fn reports_malformed_placeholders() {
+        let (result, issues) = resolve("a {{ }} b {{open", &HashMap::new());
+        assert_eq!(result, "a {{ }} b {{open");
+        let names: Vec<&str> = issues.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["{{ }}", "{{open"]);
+        assert!(issues.iter().all(|i| i.kind == IssueKind::Malformed));
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let variables = vars(&[("a", "x{{b}}"), ("b", "y{{a}}")]);
+        let (result, issues) = resolve("/{{a}}", &variables);
+        assert_eq!(result, "/xy{{a}}");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, IssueKind::Cycle);
+        assert_eq!(issues[0].name, "a -> b -> a");
+        assert_eq!(
+            issues[0].position, 1,
+            "reported at the top-level placeholder"
+        );
+        assert!(issues[0].is_fatal());
+    }
+
+    #[test]
+    fn detects_self_reference() {
+        let (_, issues) = resolve("{{a}}", &vars(&[("a", "{{a}}")]));
+        assert_eq!(issues[0].kind, IssueKind::Cycle);
+        assert_eq!(issues[0].name, "a -> a");
+    }
+
+    // v0 -> v1 -> ... -> vN, where vN is plain text
+    fn chain(links: usize) -> HashMap<String, String> {
+        let mut variables: HashMap<String, String> = (0..links)
+            .map(|i| (format!("v{}", i), format!("{{{{v{}}}}}", i + 1)))
+            .collect();
+        variables.insert(format!("v{}", links), "end".to_string());
+        variables
+    }
+
+    #[test]
+    fn follows_references_up_to_the_depth_limit() {
+        let (result, issues) = resolve("{{v0}}", &chain(MAX_DEPTH));
+        assert_eq!(result, "end");
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn stops_past_the_depth_limit() {
+        let (result, issues) = resolve("{{v0}}", &chain(MAX_DEPTH + 1));
+        assert_eq!(result, format!("{{{{v{}}}}}", MAX_DEPTH));
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, IssueKind::DepthExceeded);
+        assert!(issues[0].is_fatal());
+    }
+
+    #[test]
+    fn interpolates_enabled_request_parts() {
+        let mut request: HttpRequest = serde_json::from_value(serde_json::json!({
+            "method": "POST",
+            "url": "{{base}}/items",
+            "headers": [
+                {"key": "X-Token", "value": "{{token}}", "enabled": true},
+                {"key": "X-Off", "value": "{{missing}}", "enabled": false},
+            ],
+            "body": "{\"name\": \"{{name}}\"}",
+            "body_type": "json",
+            "auth_type": "bearer",
+            "auth_data": {"token": "{{token}}"},
+        }))
+        .unwrap();
+        let variables = vars(&[
+            ("base", "http://localhost"),
+            ("token", "t0k"),
+            ("name", "x"),
+        ]);
+
+        let issues = interpolate_request(&mut request, &variables);
+
+        assert!(issues.is_empty());
+        assert_eq!(request.url, "http://localhost/items");
+        assert_eq!(request.headers[0].value, "t0k");
+        assert_eq!(request.headers[1].value, "{{missing}}");
+        assert_eq!(request.body, "{\"name\": \"x\"}");
+        assert_eq!(request.auth_data.token.as_deref(), Some("t0k"));
+    }
+
+    #[test]
+    fn locates_issues_in_the_request() {
+        let mut request: HttpRequest = serde_json::from_value(serde_json::json!({
+            "method": "GET",
+            "url": "/",
+            "headers": [{"key": "Authorization", "value": "{{auth}}", "enabled": true}],
+            "body": "",
+            "body_type": "none",
+            "auth_type": "none",
+            "auth_data": {},
+        }))
+        .unwrap();
+
+        let issues = interpolate_request(&mut request, &HashMap::new());
+        assert_eq!(
+            describe_issues(&issues),
+            "Unresolved variables: {{auth}} in headers[Authorization]"
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use crate::db::Collection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// Where a request's response schema comes from
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum ResponseSchema {
+    Inline { schema: String },
+    File { path: String },
+    // A named schema on the request's folder or one of its parents
+    Collection { name: String },
+}
+
+// A schema stored on a collection or folder, shared by the requests inside it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NamedSchema {
+    pub name: String,
+    pub schema: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchemaViolation {
+    // JSON pointer to the offending value in the response body
+    pub pointer: String,
+    pub message: String,
+    // JSON pointer to the schema keyword that failed
+    pub schema_path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchemaValidation {
+    pub valid: bool,
+    pub violations: Vec<SchemaViolation>,
+    // Set when the schema could not be loaded or compiled
+    pub error: Option<String>,
+}
+
+// Validates a response body against the request's schema
+pub fn validate_response(
+    reference: &ResponseSchema,
+    chain: &[Collection],
+    body: &str,
+) -> SchemaValidation {
+    let validator = match load_schema(reference, chain).and_then(|schema| compile(&schema)) {
+        Ok(validator) => validator,
+        Err(error) => {
+            return SchemaValidation {
+                valid: false,
+                violations: Vec::new(),
+                error: Some(error),
+            };
+        }
+    };
+
+    let violations = match serde_json::from_str::<Value>(body) {
+        Ok(instance) => validator
+            .iter_errors(&instance)
+            .map(|e| SchemaViolation {
+                pointer: e.instance_path.to_string(),
+                message: e.to_string(),
+                schema_path: e.schema_path.to_string(),
+            })
+            .collect(),
+        Err(e) => vec![SchemaViolation {
+            pointer: String::new(),
+            message: format!("response body is not JSON: {}", e),
+            schema_path: String::new(),
+        }],
+    };
+
+    SchemaValidation {
+        valid: violations.is_empty(),
+        violations,
+        error: None,
+    }
+}
+
+fn load_schema(reference: &ResponseSchema, chain: &[Collection]) -> Result<Value, String> {
+    let (text, origin) = match reference {
+        ResponseSchema::Inline { schema } => (schema.clone(), "inline schema".to_string()),
+        ResponseSchema::File { path } => {
+            let text = std::fs::read_to_string(path.trim())
+                .map_err(|e| format!("Cannot read schema file {}: {}", path, e))?;
+            (text, path.clone())
+        }
+        // The nearest folder defining the name wins
+        ResponseSchema::Collection { name } => {
+            let found = chain.iter().find_map(|collection| {
+                serde_json::from_str::<Vec<NamedSchema>>(&collection.schemas)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .find(|s| s.name.trim() == name.trim())
+            });
+            let found = found.ok_or_else(|| {
+                format!("No schema named '{}' in this request's collection", name)
+            })?;
+            (found.schema, format!("schema '{}'", name))
+        }
+    };
+    serde_json::from_str(&text).map_err(|e| format!("Invalid JSON in {}: {}", origin, e))
+}
+
+fn compile(schema: &Value) -> Result<jsonschema::Validator, String> {
+    jsonschema::validator_for(schema).map_err(|e| format!("Invalid JSON Schema: {}", e))
+}
+
+// Checks a request's schema reference before it is saved; file and
+// collection schemas are loaded at send time
+pub fn validate_schema_reference(json: &str) -> Result<(), String> {
+    if json.trim().is_empty() {
+        return Ok(());
+    }
+    let reference: ResponseSchema =
+        serde_json::from_str(json).map_err(|e| format!("Invalid response schema: {}", e))?;
+    match reference {
+        ResponseSchema::Inline { .. } => load_schema(&reference, &[])
+            .and_then(|s| compile(&s))
+            .map(|_| ()),
+        ResponseSchema::File { path } if path.trim().is_empty() => {
+            Err("Response schema file path is empty".to_string())
+        }
+        ResponseSchema::Collection { name } if name.trim().is_empty() => {
+            Err("Response schema name is empty".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+// Checks a collection's named schemas: unique, non-empty names and valid schemas
+pub fn validate_named_schemas(json: &str) -> Result<(), String> {
+    let schemas: Vec<NamedSchema> =
+        serde_json::from_str(json).map_err(|e| format!("Invalid schemas: {}", e))?;
+    let mut names = std::collections::HashSet::new();
+    for named in &schemas {
+        let name = named.name.trim();
+        if name.is_empty() {
+            return Err("Schema names cannot be empty".to_string());
+        }
+        if !names.insert(name) {
+            return Err(format!("Duplicate schema name '{}'", name));
+        }
+        let schema: Value = serde_json::from_str(&named.schema)
+            .map_err(|e| format!("Invalid JSON in schema '{}': {}", name, e))?;
+        compile(&schema).map_err(|e| format!("Schema '{}': {}", name, e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const USER: &str =
+        r#"{"type": "object", "required": ["id"], "properties": {"id": {"type": "integer"}}}"#;
+
+    fn folder(id: &str, schemas: Value) -> Collection {
+        serde_json::from_value(json!({
+            "id": id,
+            "name": id,
+            "parent_id": null,
+            "is_folder": true,
+            "created_at": "",
+            "schemas": schemas.to_string(),
+        }))
+        .unwrap()
+    }
+
+    fn inline(schema: &str) -> ResponseSchema {
+        ResponseSchema::Inline {
+            schema: schema.to_string(),
+        }
+    }
+
+    #[test]
+    fn reports_violations_with_pointers() {
+        let validation = validate_response(&inline(USER), &[], r#"{"id": 1}"#);
+        assert!(validation.valid);
+
+        let validation = validate_response(&inline(USER), &[], r#"{"id": "1"}"#);
+        assert!(!validation.valid);
+        assert_eq!(validation.violations.len(), 1);
+        assert_eq!(validation.violations[0].pointer, "/id");
+        assert_eq!(validation.violations[0].schema_path, "/properties/id/type");
+
+        let validation = validate_response(&inline(USER), &[], "<html>");
+        assert!(!validation.valid);
+        assert!(validation.violations[0]
+            .message
+            .starts_with("response body is not JSON"));
+    }
+
+    #[test]
+    fn finds_the_nearest_named_schema() {
+        let any = json!([{"name": "user", "schema": "{}"}]);
+        let strict = json!([{"name": "user", "schema": USER}]);
+        let chain = [folder("users", strict), folder("api", any)];
+        let reference = ResponseSchema::Collection {
+            name: "user".to_string(),
+        };
+        assert!(!validate_response(&reference, &chain, "{}").valid);
+        assert!(validate_response(&reference, &chain[1..], "{}").valid);
+
+        let missing = ResponseSchema::Collection {
+            name: "order".to_string(),
+        };
+        let validation = validate_response(&missing, &chain, "{}");
+        assert!(!validation.valid);
+        assert_eq!(
+            validation.error.as_deref(),
+            Some("No schema named 'order' in this request's collection")
+        );
+    }
+
+    #[test]
+    fn reports_schemas_that_cannot_be_loaded() {
+        let file = ResponseSchema::File {
+            path: "/nonexistent/fetchr/user.json".to_string(),
+        };
+        let error = validate_response(&file, &[], "{}").error.unwrap();
+        assert!(error.starts_with("Cannot read schema file /nonexistent/fetchr/user.json"));
+
+        let error = validate_response(&inline("{"), &[], "{}").error.unwrap();
+        assert!(
+            error.starts_with("Invalid JSON in inline schema"),
+            "{}",
+            error
+        );
+        let error = validate_response(&inline(r#"{"type": 5}"#), &[], "{}")
+            .error
+            .unwrap();
+        assert!(error.starts_with("Invalid JSON Schema"), "{}", error);
+    }
+
+    #[test]
+    fn checks_schemas_before_saving() {
+        assert!(validate_schema_reference("").is_ok());
+        assert!(validate_schema_reference(r#"{"source": "file", "path": "user.json"}"#).is_ok());
+        assert_eq!(
+            validate_schema_reference(r#"{"source": "file", "path": " "}"#).unwrap_err(),
+            "Response schema file path is empty"
+        );
+        assert_eq!(
+            validate_schema_reference(r#"{"source": "collection", "name": ""}"#).unwrap_err(),
+            "Response schema name is empty"
+        );
+        let invalid = json!({"source": "inline", "schema": "{\"type\": 5}"}).to_string();
+        assert!(validate_schema_reference(&invalid).is_err());
+
+        assert!(
+            validate_named_schemas(&json!([{"name": "user", "schema": USER}]).to_string()).is_ok()
+        );
+        assert_eq!(
+            validate_named_schemas(
+                &json!([{"name": "user", "schema": "{}"}, {"name": " user ", "schema": "{}"}])
+                    .to_string()
+            )
+            .unwrap_err(),
+            "Duplicate schema name 'user'"
+        );
+        assert_eq!(
+            validate_named_schemas(&json!([{"name": "", "schema": "{}"}]).to_string()).unwrap_err(),
+            "Schema names cannot be empty"
+        );
+        let error = validate_named_schemas(&json!([{"name": "user", "schema": "{"}]).to_string())
+            .unwrap_err();
+        assert!(
+            error.starts_with("Invalid JSON in schema 'user'"),
+            "{}",
+            error
+        );
+    }
+}
@@ -1,57 +1,329 @@
+mod assertions;
+mod cli;
+mod data_file;
 mod db;
+mod dynamic;
+mod extraction;
+mod hmac_auth;
 mod http_client;
+mod inheritance;
+mod interpolation;
+mod json_schema;
 mod postman_import;
-
+mod redact;
+mod report;
+mod runner;
+mod scripting;
+mod secrets;
+mod snippet;
+mod template;
+mod variables;
+
+use assertions::{evaluate_assertions, validate_assertions};
+use data_file::read_data_file;
 use db::{Collection, Database, Environment, History, Request};
+use extraction::{extract_values, validate_extractions};
 use http_client::{send_request, HttpRequest, HttpResponse};
+use inheritance::{apply_default_headers, apply_inherited_auth};
+use interpolation::{
+    describe_issues, interpolate_request, interpolate_with_issues, VariableIssue,
+};
+use json_schema::{validate_named_schemas, validate_response, validate_schema_reference};
 use postman_import::{parse_postman_collection, ImportedCollection};
+use redact::{Redactor, REDACTION_SETTING};
+use report::{render_report, ReportFormat};
+use runner::{RunEvent, RunOptions, RunSummary};
+use secrets::{map_auth_secrets, map_variable_secrets, Vault, VaultStatus};
+use snippet::curl_command;
+use scripting::{ScriptEvent, ScriptResults, ScriptRunner};
+use variables::{
+    build_context, check_environment_parent, environment_ids, migrate_variable_schema,
+    validate_variables, ResolvedVariable, VariableContext,
+};
 use std::sync::Mutex;
-use tauri::{Manager, State};
+use tauri::{Emitter, Manager, State};
 
 struct AppState {
     db: Mutex<Database>,
+    vault: Mutex<Vault>,
 }
 
 // HTTP Client Commands
 #[tauri::command]
-async fn send_http_request(request: HttpRequest) -> Result<HttpResponse, String> {
-    send_request(request).await
+async fn send_http_request(
+    request: HttpRequest,
+    state: State<'_, AppState>,
+) -> Result<HttpResponse, String> {
+    execute_request(&state, request).await
 }
 
-#[tauri::command]
-fn interpolate_variables(text: String, state: State<AppState>) -> Result<String, String> {
-    let db = state.db.lock().unwrap();
-    let env = db.get_active_environment().map_err(|e| e.to_string())?;
+// Sends a request with folder settings, scripts, assertions, schema
+// validation and extraction rules applied
+async fn execute_request(
+    state: &AppState,
+    mut request: HttpRequest,
+) -> Result<HttpResponse, String> {
+    let mut scripts = ScriptResults::default();
+    let prepared = {
+        let db = state.db.lock().unwrap();
+        let vault = state.vault.lock().unwrap();
+        prepare_request(&db, &vault, &mut request, Some(&mut scripts))?
+    };
+    let PreparedRequest {
+        issues,
+        chain,
+        mut context,
+    } = prepared;
+    if let Some(issue) = issues.iter().find(|i| i.is_fatal()) {
+        return Err(format!("Cannot send request: {}", issue));
+    }
+    if request.strict_variables && !issues.is_empty() {
+        return Err(describe_issues(&issues));
+    }
+
+    let resolved = request.clone();
+    let mut response = send_request(request).await?;
+    response.assertion_results = evaluate_assertions(&resolved.assertions, &response);
 
-    if let Some(env) = env {
-        let variables: Vec<serde_json::Value> =
-            serde_json::from_str(&env.variables).unwrap_or_default();
-        let mut result = text;
+    if let Some(schema) = &resolved.response_schema {
+        response.schema_validation = Some(validate_response(schema, &chain, &response.body));
+    }
 
-        for var in variables {
-            if let (Some(key), Some(value)) = (var["key"].as_str(), var["value"].as_str()) {
-                let pattern = format!("{{{{{}}}}}", key);
-                result = result.replace(&pattern, value);
+    {
+        let db = state.db.lock().unwrap();
+        let vault = state.vault.lock().unwrap();
+        let runner = script_runner(&db, &vault, &chain, &resolved)?;
+
+        // Extracted values are visible to the test scripts that follow
+        response.extractions = extract_values(
+            &resolved.extractions,
+            &response,
+            !chain.is_empty(),
+            runner.environment_id.is_some(),
+        );
+        // A value that can't be saved (say, a secret variable while the vault
+        // is locked) is reported on its rule rather than losing the response
+        for extraction in response.extractions.iter_mut() {
+            if let Some(update) = extraction.update()
+                && let Err(e) = runner.save(&[update], &mut context)
+            {
+                extraction.error = Some(format!("Could not save: {}", e));
             }
         }
 
-        Ok(result)
-    } else {
-        Ok(text)
+        runner.run(
+            ScriptEvent::Test,
+            &mut resolved.clone(),
+            &mut context,
+            Some(&response),
+            &mut scripts,
+        )?;
+    }
+
+    response.resolved_request = Some(resolved);
+    response.variable_issues = issues;
+    response.script_logs = scripts.logs;
+    response.test_results = scripts.tests;
+    Ok(response)
+}
+
+// Runs every request under a collection or folder, once per row of the
+// data file when one is given, emitting a `collection-run-progress` event
+// as each request starts and finishes
+#[tauri::command]
+async fn run_collection(
+    collection_id: String,
+    environment_ids: Option<Vec<String>>,
+    data_file: Option<String>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<RunSummary, String> {
+    let mut options = RunOptions {
+        environment_ids,
+        ..RunOptions::default()
+    };
+    if let Some(path) = data_file.filter(|path| !path.trim().is_empty()) {
+        options.iterations = read_data_file(&path)?;
+    }
+    runner::run_collection(&state, &collection_id, &options, |event: RunEvent| {
+        app.emit("collection-run-progress", &event).ok();
+    })
+    .await
+}
+
+// Renders a finished run as JUnit XML, JSON or a self-contained HTML page
+#[tauri::command]
+fn render_run_report(summary: RunSummary, format: ReportFormat) -> Result<String, String> {
+    render_report(&summary, format)
+}
+
+// Reports the placeholders a request would be sent with unresolved
+#[tauri::command]
+fn check_request_variables(
+    mut request: HttpRequest,
+    state: State<AppState>,
+) -> Result<Vec<VariableIssue>, String> {
+    let db = state.db.lock().unwrap();
+    let vault = state.vault.lock().unwrap();
+    Ok(prepare_request(&db, &vault, &mut request, None)?.issues)
+}
+
+struct PreparedRequest {
+    issues: Vec<VariableIssue>,
+    chain: Vec<Collection>,
+    context: VariableContext,
+}
+
+// Applies folder defaults, decrypts credentials, runs pre-request scripts
+// (when `scripts` is given) and resolves variables
+fn prepare_request(
+    db: &Database,
+    vault: &Vault,
+    request: &mut HttpRequest,
+    scripts: Option<&mut ScriptResults>,
+) -> Result<PreparedRequest, String> {
+    let chain = match &request.collection_id {
+        Some(collection_id) => db
+            .get_collection_chain(collection_id)
+            .map_err(|e| e.to_string())?,
+        None => Vec::new(),
+    };
+    apply_inherited_auth(request, &chain)?;
+    apply_default_headers(request, &chain)?;
+    vault.open_auth(&mut request.auth_data)?;
+
+    let mut context = build_context(
+        db,
+        vault,
+        &chain,
+        request.environment_ids.as_deref(),
+        &request.iteration_data,
+        &request.variables,
+    )?;
+    if let Some(scripts) = scripts {
+        let runner = script_runner(db, vault, &chain, request)?;
+        runner.run(ScriptEvent::PreRequest, request, &mut context, None, scripts)?;
+    }
+
+    let issues = interpolate_request(request, &context.values());
+    Ok(PreparedRequest {
+        issues,
+        chain,
+        context,
+    })
+}
+
+// Script changes to environment variables go to the last environment the send uses
+fn script_runner<'a>(
+    db: &'a Database,
+    vault: &'a Vault,
+    chain: &'a [Collection],
+    request: &HttpRequest,
+) -> Result<ScriptRunner<'a>, String> {
+    let environment_id = environment_ids(db, request.environment_ids.as_deref())?
+        .last()
+        .cloned();
+    Ok(ScriptRunner {
+        db,
+        vault,
+        chain,
+        environment_id,
+    })
+}
+
+#[tauri::command]
+fn interpolate_variables(
+    text: String,
+    environment_ids: Option<Vec<String>>,
+    state: State<AppState>,
+) -> Result<String, String> {
+    let db = state.db.lock().unwrap();
+    let vault = state.vault.lock().unwrap();
+    let context = build_context(&db, &vault, &[], environment_ids.as_deref(), &[], &[])?;
+    let mut issues = Vec::new();
+    let result = interpolate_with_issues(&text, &context.values(), "text", &mut issues);
+    match issues.iter().find(|i| i.is_fatal()) {
+        Some(issue) => Err(issue.to_string()),
+        None => Ok(result),
     }
 }
 
+// Lists the variables in effect for a request and the scope each one comes from
+#[tauri::command]
+fn explain_variables(
+    request: HttpRequest,
+    state: State<AppState>,
+) -> Result<Vec<ResolvedVariable>, String> {
+    let db = state.db.lock().unwrap();
+    let vault = state.vault.lock().unwrap();
+    let chain = match &request.collection_id {
+        Some(collection_id) => db
+            .get_collection_chain(collection_id)
+            .map_err(|e| e.to_string())?,
+        None => Vec::new(),
+    };
+    let context = build_context(
+        &db,
+        &vault,
+        &chain,
+        request.environment_ids.as_deref(),
+        &request.iteration_data,
+        &request.variables,
+    )?;
+    Ok(context.explain())
+}
+
+// Global Variable Commands
+#[tauri::command]
+fn get_global_variables(state: State<AppState>) -> Result<String, String> {
+    let db = state.db.lock().unwrap();
+    let vault = state.vault.lock().unwrap();
+    let variables = db.get_global_variables().map_err(|e| e.to_string())?;
+    map_variable_secrets(&variables, |v| vault.reveal(v))
+}
+
+#[tauri::command]
+fn save_global_variables(variables: String, state: State<AppState>) -> Result<(), String> {
+    let db = state.db.lock().unwrap();
+    let vault = state.vault.lock().unwrap();
+    let variables = map_variable_secrets(&validate_variables(&variables)?, |v| vault.seal(v))?;
+    db.save_global_variables(&variables)
+        .map_err(|e| e.to_string())
+}
+
 // Collection Commands
 #[tauri::command]
-fn create_collection(collection: Collection, state: State<AppState>) -> Result<(), String> {
+fn create_collection(mut collection: Collection, state: State<AppState>) -> Result<(), String> {
     let db = state.db.lock().unwrap();
+    let vault = state.vault.lock().unwrap();
+    collection.auth_data = map_auth_secrets(&collection.auth_data, |v| vault.seal(v))?;
+    let variables = validate_variables(&collection.variables)?;
+    collection.variables = map_variable_secrets(&variables, |v| vault.seal(v))?;
+    validate_named_schemas(&collection.schemas)?;
     db.create_collection(&collection).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+fn update_collection(mut collection: Collection, state: State<AppState>) -> Result<(), String> {
+    let db = state.db.lock().unwrap();
+    let vault = state.vault.lock().unwrap();
+    collection.auth_data = map_auth_secrets(&collection.auth_data, |v| vault.seal(v))?;
+    let variables = validate_variables(&collection.variables)?;
+    collection.variables = map_variable_secrets(&variables, |v| vault.seal(v))?;
+    validate_named_schemas(&collection.schemas)?;
+    db.update_collection(&collection).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 fn get_all_collections(state: State<AppState>) -> Result<Vec<Collection>, String> {
     let db = state.db.lock().unwrap();
-    db.get_all_collections().map_err(|e| e.to_string())
+    let vault = state.vault.lock().unwrap();
+    let mut collections = db.get_all_collections().map_err(|e| e.to_string())?;
+    for collection in collections.iter_mut() {
+        collection.auth_data = map_auth_secrets(&collection.auth_data, |v| vault.reveal(v))?;
+        collection.variables = map_variable_secrets(&collection.variables, |v| vault.reveal(v))?;
+    }
+    Ok(collections)
 }
 
 #[tauri::command]
@@ -62,8 +334,13 @@ fn delete_collection(id: String, state: State<AppState>) -> Result<(), String> {
 
 // Request Commands
 #[tauri::command]
-fn save_request(request: Request, state: State<AppState>) -> Result<(), String> {
+fn save_request(mut request: Request, state: State<AppState>) -> Result<(), String> {
     let db = state.db.lock().unwrap();
+    let vault = state.vault.lock().unwrap();
+    request.auth_data = map_auth_secrets(&request.auth_data, |v| vault.seal(v))?;
+    validate_assertions(&request.assertions)?;
+    validate_extractions(&request.extractions)?;
+    validate_schema_reference(&request.response_schema)?;
     db.save_request(&request).map_err(|e| e.to_string())
 }
 
@@ -73,14 +350,25 @@ fn get_requests_by_collection(
     state: State<AppState>,
 ) -> Result<Vec<Request>, String> {
     let db = state.db.lock().unwrap();
-    db.get_requests_by_collection(&collection_id)
-        .map_err(|e| e.to_string())
+    let vault = state.vault.lock().unwrap();
+    let mut requests = db
+        .get_requests_by_collection(&collection_id)
+        .map_err(|e| e.to_string())?;
+    for request in requests.iter_mut() {
+        request.auth_data = map_auth_secrets(&request.auth_data, |v| vault.reveal(v))?;
+    }
+    Ok(requests)
 }
 
 #[tauri::command]
 fn get_request(id: String, state: State<AppState>) -> Result<Option<Request>, String> {
     let db = state.db.lock().unwrap();
-    db.get_request(&id).map_err(|e| e.to_string())
+    let vault = state.vault.lock().unwrap();
+    let mut request = db.get_request(&id).map_err(|e| e.to_string())?;
+    if let Some(request) = request.as_mut() {
+        request.auth_data = map_auth_secrets(&request.auth_data, |v| vault.reveal(v))?;
+    }
+    Ok(request)
 }
 
 #[tauri::command]
@@ -91,21 +379,35 @@ fn delete_request(id: String, state: State<AppState>) -> Result<(), String> {
 
 // Environment Commands
 #[tauri::command]
-fn save_environment(env: Environment, state: State<AppState>) -> Result<(), String> {
+fn save_environment(mut env: Environment, state: State<AppState>) -> Result<(), String> {
     let db = state.db.lock().unwrap();
+    let vault = state.vault.lock().unwrap();
+    check_environment_parent(&db, &env)?;
+    let variables = validate_variables(&env.variables)?;
+    env.variables = map_variable_secrets(&variables, |v| vault.seal(v))?;
     db.save_environment(&env).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 fn get_all_environments(state: State<AppState>) -> Result<Vec<Environment>, String> {
     let db = state.db.lock().unwrap();
-    db.get_all_environments().map_err(|e| e.to_string())
+    let vault = state.vault.lock().unwrap();
+    let mut envs = db.get_all_environments().map_err(|e| e.to_string())?;
+    for env in envs.iter_mut() {
+        env.variables = map_variable_secrets(&env.variables, |v| vault.reveal(v))?;
+    }
+    Ok(envs)
 }
 
 #[tauri::command]
 fn get_active_environment(state: State<AppState>) -> Result<Option<Environment>, String> {
     let db = state.db.lock().unwrap();
-    db.get_active_environment().map_err(|e| e.to_string())
+    let vault = state.vault.lock().unwrap();
+    let mut env = db.get_active_environment().map_err(|e| e.to_string())?;
+    if let Some(env) = env.as_mut() {
+        env.variables = map_variable_secrets(&env.variables, |v| vault.reveal(v))?;
+    }
+    Ok(env)
 }
 
 #[tauri::command]
@@ -116,8 +418,21 @@ fn delete_environment(id: String, state: State<AppState>) -> Result<(), String>
 
 // History Commands
 #[tauri::command]
-fn add_history(history: History, state: State<AppState>) -> Result<(), String> {
+fn add_history(
+    mut history: History,
+    collection_id: Option<String>,
+    environment_ids: Option<Vec<String>>,
+    state: State<AppState>,
+) -> Result<(), String> {
     let db = state.db.lock().unwrap();
+    let vault = state.vault.lock().unwrap();
+    let redactor = send_redactor(
+        &db,
+        &vault,
+        collection_id.as_deref(),
+        environment_ids.as_deref(),
+    )?;
+    history.url = redactor.text(&history.url);
     db.add_history(&history).map_err(|e| e.to_string())
 }
 
@@ -133,6 +448,107 @@ fn clear_history(state: State<AppState>) -> Result<(), String> {
     db.clear_history().map_err(|e| e.to_string())
 }
 
+// Vault Commands
+#[tauri::command]
+fn get_vault_status(state: State<AppState>) -> VaultStatus {
+    state.vault.lock().unwrap().status()
+}
+
+#[tauri::command]
+fn setup_vault(passphrase: String, state: State<AppState>) -> Result<(), String> {
+    let db = state.db.lock().unwrap();
+    let mut vault = state.vault.lock().unwrap();
+    vault.setup(&db, &passphrase)?;
+
+    // Encrypt credentials and secret variables that were saved in plaintext
+    for mut collection in db.get_all_collections().map_err(|e| e.to_string())? {
+        for mut request in db
+            .get_requests_by_collection(&collection.id)
+            .map_err(|e| e.to_string())?
+        {
+            request.auth_data = map_auth_secrets(&request.auth_data, |v| vault.seal(v))?;
+            db.save_request(&request).map_err(|e| e.to_string())?;
+        }
+        collection.auth_data = map_auth_secrets(&collection.auth_data, |v| vault.seal(v))?;
+        collection.variables = map_variable_secrets(&collection.variables, |v| vault.seal(v))?;
+        db.update_collection(&collection).map_err(|e| e.to_string())?;
+    }
+    let globals = db.get_global_variables().map_err(|e| e.to_string())?;
+    db.save_global_variables(&map_variable_secrets(&globals, |v| vault.seal(v))?)
+        .map_err(|e| e.to_string())?;
+    for mut env in db.get_all_environments().map_err(|e| e.to_string())? {
+        env.variables = map_variable_secrets(&env.variables, |v| vault.seal(v))?;
+        db.save_environment(&env).map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+fn unlock_vault(passphrase: String, state: State<AppState>) -> Result<(), String> {
+    let db = state.db.lock().unwrap();
+    let mut vault = state.vault.lock().unwrap();
+    vault.unlock(&db, &passphrase)
+}
+
+#[tauri::command]
+fn lock_vault(state: State<AppState>) {
+    state.vault.lock().unwrap().lock();
+}
+
+// Redaction Commands
+#[tauri::command]
+fn get_redaction_enabled(state: State<AppState>) -> Result<bool, String> {
+    let db = state.db.lock().unwrap();
+    redaction_enabled(&db)
+}
+
+#[tauri::command]
+fn set_redaction_enabled(enabled: bool, state: State<AppState>) -> Result<(), String> {
+    let db = state.db.lock().unwrap();
+    db.set_setting(REDACTION_SETTING, &enabled.to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn generate_curl(request: HttpRequest, state: State<AppState>) -> Result<String, String> {
+    let db = state.db.lock().unwrap();
+    let vault = state.vault.lock().unwrap();
+    let redactor = send_redactor(
+        &db,
+        &vault,
+        request.collection_id.as_deref(),
+        request.environment_ids.as_deref(),
+    )?;
+    Ok(curl_command(&redactor.request(&request)))
+}
+
+fn redaction_enabled(db: &Database) -> Result<bool, String> {
+    let setting = db
+        .get_setting(REDACTION_SETTING)
+        .map_err(|e| e.to_string())?;
+    Ok(setting.as_deref() != Some("false"))
+}
+
+// Redactor covering the secret variables a send resolves against: globals, the
+// collection and its parent folders, and the environments it selects (or else
+// the active one) with their parents
+fn send_redactor(
+    db: &Database,
+    vault: &Vault,
+    collection_id: Option<&str>,
+    environment_ids: Option<&[String]>,
+) -> Result<Redactor, String> {
+    let chain = match collection_id {
+        Some(collection_id) => db
+            .get_collection_chain(collection_id)
+            .map_err(|e| e.to_string())?,
+        None => Vec::new(),
+    };
+    let context = build_context(db, vault, &chain, environment_ids, &[], &[])?;
+    Ok(Redactor::new(redaction_enabled(db)?, context.secrets().to_vec()))
+}
+
 // Import/Export Commands
 #[tauri::command]
 async fn import_postman_collection(json_content: String) -> Result<ImportedCollection, String> {
@@ -145,7 +561,16 @@ fn save_imported_collection(
     state: State<AppState>,
 ) -> Result<String, String> {
     let db = state.db.lock().unwrap();
+    let vault = state.vault.lock().unwrap();
+    save_imported(&db, &vault, imported)
+}
 
+// Saves an imported collection with its folders and requests, returning the root's id
+fn save_imported(
+    db: &Database,
+    vault: &Vault,
+    imported: ImportedCollection,
+) -> Result<String, String> {
     // Create root collection
     let root_id = uuid::Uuid::new_v4().to_string();
     let root_collection = Collection {
@@ -154,6 +579,16 @@ fn save_imported_collection(
         parent_id: None,
         is_folder: true,
         created_at: chrono::Utc::now().to_rfc3339(),
+        auth_type: imported.auth_type,
+        auth_data: map_auth_secrets(&imported.auth_data, |v| vault.seal(v))?,
+        headers: serde_json::to_string(&imported.headers).unwrap_or_default(),
+        variables: map_variable_secrets(
+            &serde_json::to_string(&imported.variables).unwrap_or_default(),
+            |v| vault.seal(v),
+        )?,
+        pre_request_script: imported.pre_request_script,
+        test_script: imported.test_script,
+        schemas: serde_json::to_string(&imported.schemas).unwrap_or_default(),
     };
     db.create_collection(&root_collection).map_err(|e| e.to_string())?;
 
@@ -171,6 +606,16 @@ fn save_imported_collection(
             parent_id,
             is_folder: true,
             created_at: chrono::Utc::now().to_rfc3339(),
+            auth_type: folder.auth_type,
+            auth_data: map_auth_secrets(&folder.auth_data, |v| vault.seal(v))?,
+            headers: serde_json::to_string(&folder.headers).unwrap_or_default(),
+            variables: map_variable_secrets(
+                &serde_json::to_string(&folder.variables).unwrap_or_default(),
+                |v| vault.seal(v),
+            )?,
+            pre_request_script: folder.pre_request_script,
+            test_script: folder.test_script,
+            schemas: serde_json::to_string(&folder.schemas).unwrap_or_default(),
         };
         db.create_collection(&collection).map_err(|e| e.to_string())?;
 
@@ -196,9 +641,18 @@ fn save_imported_collection(
             body: request.body,
             body_type: request.body_type,
             auth_type: request.auth_type,
-            auth_data: request.auth_data,
+            auth_data: map_auth_secrets(&request.auth_data, |v| vault.seal(v))?,
             created_at: chrono::Utc::now().to_rfc3339(),
             updated_at: chrono::Utc::now().to_rfc3339(),
+            variables: serde_json::to_string(&request.variables).unwrap_or_default(),
+            pre_request_script: request.pre_request_script,
+            test_script: request.test_script,
+            assertions: serde_json::to_string(&request.assertions).unwrap_or_default(),
+            extractions: serde_json::to_string(&request.extractions).unwrap_or_default(),
+            response_schema: match &request.response_schema {
+                Some(schema) => serde_json::to_string(schema).unwrap_or_default(),
+                None => String::new(),
+            },
         };
         db.save_request(&req).map_err(|e| e.to_string())?;
     }
@@ -209,6 +663,7 @@ fn save_imported_collection(
 #[tauri::command]
 fn export_collection(collection_id: String, state: State<AppState>) -> Result<String, String> {
     let db = state.db.lock().unwrap();
+    let vault = state.vault.lock().unwrap();
 
     // Get the collection
     let collections = db.get_all_collections().map_err(|e| e.to_string())?;
@@ -216,32 +671,89 @@ fn export_collection(collection_id: String, state: State<AppState>) -> Result<St
         .find(|c| c.id == collection_id)
         .ok_or_else(|| "Collection not found".to_string())?;
 
+    // Mask the secrets of its parents and of every exported subfolder too
+    let mut scope = db.get_collection_chain(&collection_id)
+        .map_err(|e| e.to_string())?;
+    scope.extend(subfolders(&collections, &collection_id));
+    let context = build_context(&db, &vault, &scope, None, &[], &[])?;
+    let redactor = Redactor::new(redaction_enabled(&db)?, context.secrets().to_vec());
+
+    let export_data = export_folder(&db, &vault, &redactor, &collections, collection)?;
+    serde_json::to_string_pretty(&export_data).map_err(|e| e.to_string())
+}
+
+// Every folder below a collection, at any depth
+fn subfolders(collections: &[Collection], collection_id: &str) -> Vec<Collection> {
+    let mut found = Vec::new();
+    for child in collections.iter().filter(|c| c.parent_id.as_deref() == Some(collection_id)) {
+        found.push(child.clone());
+        found.extend(subfolders(collections, &child.id));
+    }
+    found
+}
+
+// Exports a collection or folder with its requests and subfolders, nested
+fn export_folder(
+    db: &Database,
+    vault: &Vault,
+    redactor: &Redactor,
+    collections: &[Collection],
+    collection: &Collection,
+) -> Result<serde_json::Value, String> {
+    let export_auth = |auth_data: &str| -> Result<serde_json::Value, String> {
+        let revealed = map_auth_secrets(auth_data, |v| vault.reveal(v))?;
+        Ok(serde_json::from_str(&redactor.auth_data_json(&revealed)).unwrap_or(serde_json::json!({})))
+    };
+    let json_or = |json: &str, default: serde_json::Value| {
+        serde_json::from_str::<serde_json::Value>(json).unwrap_or(default)
+    };
+
+    let mut folders = Vec::new();
+    for child in collections.iter().filter(|c| c.parent_id.as_deref() == Some(collection.id.as_str())) {
+        folders.push(export_folder(db, vault, redactor, collections, child)?);
+    }
+
     // Get all requests in this collection
-    let requests = db.get_requests_by_collection(&collection_id)
+    let requests = db.get_requests_by_collection(&collection.id)
         .map_err(|e| e.to_string())?;
+    let mut exported_requests = Vec::new();
+    for request in requests {
+        exported_requests.push(serde_json::json!({
+            "name": request.name,
+            "method": request.method,
+            "url": redactor.text(&request.url),
+            "headers": json_or(&redactor.headers_json(&request.headers), serde_json::json!([])),
+            "body": redactor.text(&request.body),
+            "body_type": request.body_type,
+            "auth_type": request.auth_type,
+            "auth_data": export_auth(&request.auth_data)?,
+            "variables": json_or(&redactor.text(&request.variables), serde_json::json!([])),
+            "pre_request_script": redactor.text(&request.pre_request_script),
+            "test_script": redactor.text(&request.test_script),
+            "assertions": json_or(&redactor.text(&request.assertions), serde_json::json!([])),
+            "extractions": json_or(&request.extractions, serde_json::json!([])),
+            "response_schema": json_or(&request.response_schema, serde_json::Value::Null),
+        }));
+    }
 
     // Build export JSON
-    let mut export_data = serde_json::json!({
+    Ok(serde_json::json!({
         "name": collection.name,
-        "requests": []
-    });
-
-    if let Some(requests_array) = export_data["requests"].as_array_mut() {
-        for request in requests {
-            requests_array.push(serde_json::json!({
-                "name": request.name,
-                "method": request.method,
-                "url": request.url,
-                "headers": serde_json::from_str::<serde_json::Value>(&request.headers).unwrap_or(serde_json::json!([])),
-                "body": request.body,
-                "body_type": request.body_type,
-                "auth_type": request.auth_type,
-                "auth_data": serde_json::from_str::<serde_json::Value>(&request.auth_data).unwrap_or(serde_json::json!({})),
-            }));
-        }
-    }
+        "auth_type": collection.auth_type,
+        "auth_data": export_auth(&collection.auth_data)?,
+        "headers": json_or(&redactor.headers_json(&collection.headers), serde_json::json!([])),
+        "variables": json_or(&redactor.variables_json(&map_variable_secrets(&collection.variables, |v| vault.reveal(v))?), serde_json::json!([])),
+        "pre_request_script": redactor.text(&collection.pre_request_script),
+        "test_script": redactor.text(&collection.test_script),
+        "schemas": json_or(&collection.schemas, serde_json::json!([])),
+        "folders": folders,
+        "requests": exported_requests,
+    }))
+}
 
-    serde_json::to_string_pretty(&export_data).map_err(|e| e.to_string())
+// Entry point of the fetchr-cli binary; returns the process exit code
+pub fn run_cli() -> i32 {
+    cli::run()
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -259,14 +771,26 @@ pub fn run() {
             std::fs::create_dir_all(db_path.parent().unwrap()).ok();
 
             let db = Database::new(db_path.to_str().unwrap()).expect("Failed to initialize database");
-            app.manage(AppState { db: Mutex::new(db) });
+            migrate_variable_schema(&db).expect("Failed to migrate variables");
+            let vault = Vault::load(&db).expect("Failed to load vault settings");
+            app.manage(AppState {
+                db: Mutex::new(db),
+                vault: Mutex::new(vault),
+            });
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             send_http_request,
+            run_collection,
+            render_run_report,
+            check_request_variables,
             interpolate_variables,
+            explain_variables,
+            get_global_variables,
+            save_global_variables,
             create_collection,
+            update_collection,
             get_all_collections,
             delete_collection,
             save_request,
@@ -280,6 +804,13 @@ pub fn run() {
             add_history,
             get_history,
             clear_history,
+            get_vault_status,
+            setup_vault,
+            unlock_vault,
+            lock_vault,
+            get_redaction_enabled,
+            set_redaction_enabled,
+            generate_curl,
             import_postman_collection,
             save_imported_collection,
             export_collection,
@@ -287,3 +818,90 @@ pub fn run() {
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn environment(id: &str, variables: serde_json::Value, parent_id: Option<&str>) -> Environment {
+        Environment {
+            id: id.to_string(),
+            name: id.to_string(),
+            variables: variables.to_string(),
+            is_active: id == "active",
+            created_at: String::new(),
+            dotenv_path: None,
+            parent_id: parent_id.map(|p| p.to_string()),
+        }
+    }
+
+    fn secret(key: &str, value: &str) -> serde_json::Value {
+        json!({"key": key, "value": value, "secret": true})
+    }
+
+    #[test]
+    fn send_redactor_covers_the_selected_environments() {
+        let db = Database::new(":memory:").unwrap();
+        let vault = Vault::default();
+        let environments = [
+            environment("active", json!([secret("token", "active-secret")]), None),
+            environment("base", json!([secret("key", "base-secret")]), None),
+            environment(
+                "staging",
+                json!([secret("token", "staging-secret"), {"key": "host", "value": "staging.example.com"}]),
+                Some("base"),
+            ),
+        ];
+        for env in &environments {
+            db.save_environment(env).unwrap();
+        }
+        let url = "https://staging.example.com/?a=active-secret&b=staging-secret&c=base-secret";
+
+        let redactor = send_redactor(&db, &vault, None, Some(&["staging".to_string()])).unwrap();
+        assert_eq!(
+            redactor.text(url),
+            "https://staging.example.com/?a=active-secret&b=********&c=********"
+        );
+
+        let redactor = send_redactor(&db, &vault, None, None).unwrap();
+        assert_eq!(
+            redactor.text(url),
+            "https://staging.example.com/?a=********&b=staging-secret&c=base-secret"
+        );
+    }
+    #[test]
+    fn send_redactor_covers_the_collection_chain() {
+        let db = Database::new(":memory:").unwrap();
+        let vault = Vault::default();
+        let collection = |id: &str, parent_id: Option<&str>, variables: serde_json::Value| {
+            serde_json::from_value::<Collection>(json!({
+                "id": id,
+                "name": id,
+                "parent_id": parent_id,
+                "is_folder": parent_id.is_some(),
+                "created_at": "",
+                "variables": variables.to_string(),
+            }))
+            .unwrap()
+        };
+        for c in [
+            collection("api", None, json!([secret("client", "collection-secret")])),
+            collection(
+                "users",
+                Some("api"),
+                json!([secret("key", "folder-secret")]),
+            ),
+            collection("other", None, json!([secret("key", "other-secret")])),
+        ] {
+            db.create_collection(&c).unwrap();
+        }
+        let body = "collection-secret folder-secret other-secret";
+
+        let redactor = send_redactor(&db, &vault, Some("users"), None).unwrap();
+        assert_eq!(redactor.text(body), "******** ******** other-secret");
+
+        let redactor = send_redactor(&db, &vault, Some("api"), None).unwrap();
+        assert_eq!(redactor.text(body), "******** folder-secret other-secret");
+    }
+}
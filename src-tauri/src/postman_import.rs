@@ -1,3 +1,9 @@
+use crate::assertions::Assertion;
+use crate::extraction::ExtractionRule;
+use crate::http_client::KeyValue;
+use crate::json_schema::{NamedSchema, ResponseSchema};
+use crate::scripting::unsupported_apis;
+use crate::variables::{Variable, VariableType};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -5,6 +11,49 @@ use serde_json::Value;
 pub struct PostmanCollection {
     pub info: PostmanInfo,
     pub item: Vec<PostmanItem>,
+    pub auth: Option<Value>,
+    pub header: Option<Vec<PostmanHeader>>, // Collection-level default headers
+    pub variable: Option<Vec<PostmanVariable>>,
+    pub event: Option<Vec<PostmanEvent>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostmanEvent {
+    pub listen: String,
+    pub script: Option<PostmanScript>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostmanScript {
+    pub exec: Option<Value>, // An array of lines or a single string
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostmanVariable {
+    pub key: String,
+    pub value: Option<Value>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+// An environment as exported from Postman
+#[derive(Debug, Deserialize)]
+pub struct PostmanEnvironment {
+    pub name: String,
+    #[serde(default)]
+    pub values: Vec<PostmanEnvironmentValue>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostmanEnvironmentValue {
+    pub key: String,
+    pub value: Option<Value>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(rename = "type")]
+    pub value_type: Option<String>, // "default" or "secret"
 }
 
 #[derive(Debug, Deserialize)]
@@ -19,6 +68,8 @@ pub struct PostmanItem {
     pub name: String,
     pub item: Option<Vec<PostmanItem>>, // For folders
     pub request: Option<PostmanRequest>,
+    pub auth: Option<Value>, // Folder-level auth
+    pub event: Option<Vec<PostmanEvent>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -74,14 +125,39 @@ pub struct PostmanUrlObject {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ImportedCollection {
     pub name: String,
+    pub auth_type: String,
+    pub auth_data: String,
+    pub headers: Vec<ImportedHeader>,
+    pub variables: Vec<ImportedVariable>,
     pub folders: Vec<ImportedFolder>,
     pub requests: Vec<ImportedRequest>,
+    #[serde(default)]
+    pub pre_request_script: String,
+    #[serde(default)]
+    pub test_script: String,
+    #[serde(default)]
+    pub schemas: Vec<NamedSchema>,
+    // Scripts using APIs the local runtime doesn't support
+    #[serde(default)]
+    pub warnings: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ImportedFolder {
     pub name: String,
     pub parent_path: Vec<String>,
+    pub auth_type: String,
+    pub auth_data: String,
+    #[serde(default)]
+    pub headers: Vec<ImportedHeader>,
+    #[serde(default)]
+    pub variables: Vec<ImportedVariable>,
+    #[serde(default)]
+    pub pre_request_script: String,
+    #[serde(default)]
+    pub test_script: String,
+    #[serde(default)]
+    pub schemas: Vec<NamedSchema>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -96,6 +172,18 @@ pub struct ImportedRequest {
     pub auth_data: String,
     pub form_data: Vec<ImportedFormData>,
     pub folder_path: Vec<String>,
+    #[serde(default)]
+    pub variables: Vec<KeyValue>,
+    #[serde(default)]
+    pub pre_request_script: String,
+    #[serde(default)]
+    pub test_script: String,
+    #[serde(default)]
+    pub assertions: Vec<Assertion>,
+    #[serde(default)]
+    pub extractions: Vec<ExtractionRule>,
+    #[serde(default)]
+    pub response_schema: Option<ResponseSchema>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -105,6 +193,18 @@ pub struct ImportedHeader {
     pub enabled: bool,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportedVariable {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportedEnvironment {
+    pub name: String,
+    pub variables: Vec<Variable>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ImportedFormData {
     pub key: String,
@@ -121,32 +221,113 @@ pub fn parse_postman_collection(json_content: &str) -> Result<ImportedCollection
 
     let mut folders = Vec::new();
     let mut requests = Vec::new();
-
-    process_items(&collection.item, &mut folders, &mut requests, &mut Vec::new())?;
+    let mut warnings = Vec::new();
+
+    let (pre_request_script, test_script) = parse_events(
+        collection.event.as_deref(),
+        &collection.info.name,
+        &mut warnings,
+    );
+    process_items(&collection.item, &mut folders, &mut requests, &mut warnings, &mut Vec::new())?;
+
+    let (auth_type, auth_data) = parse_inheritable_auth(collection.auth.as_ref());
+    let headers = parse_headers(collection.header.as_deref());
+    let variables = collection
+        .variable
+        .as_ref()
+        .map(|vars| {
+            vars.iter()
+                .filter(|v| !v.disabled)
+                .map(|v| ImportedVariable {
+                    key: v.key.clone(),
+                    value: variable_value(v.value.as_ref()),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
 
     Ok(ImportedCollection {
         name: collection.info.name,
+        auth_type,
+        auth_data,
+        headers,
+        variables,
         folders,
         requests,
+        pre_request_script,
+        test_script,
+        schemas: Vec::new(),
+        warnings,
+    })
+}
+
+pub fn parse_postman_environment(json_content: &str) -> Result<ImportedEnvironment, String> {
+    let environment: PostmanEnvironment = serde_json::from_str(json_content)
+        .map_err(|e| format!("Invalid Postman environment: {}", e))?;
+
+    let variables = environment
+        .values
+        .into_iter()
+        .filter(|v| !v.key.trim().is_empty())
+        .map(|v| Variable {
+            key: v.key,
+            value: variable_value(v.value.as_ref()),
+            initial_value: None,
+            enabled: v.enabled,
+            secret: v.value_type.as_deref() == Some("secret"),
+            kind: VariableType::String,
+            description: String::new(),
+        })
+        .collect();
+
+    Ok(ImportedEnvironment {
+        name: environment.name,
+        variables,
     })
 }
 
+fn variable_value(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    }
+}
+
 fn process_items(
     items: &[PostmanItem],
     folders: &mut Vec<ImportedFolder>,
     requests: &mut Vec<ImportedRequest>,
+    warnings: &mut Vec<String>,
     current_path: &mut Vec<String>,
 ) -> Result<(), String> {
     for item in items {
+        let location = current_path
+            .iter()
+            .chain(std::iter::once(&item.name))
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("/");
+        let (pre_request_script, test_script) =
+            parse_events(item.event.as_deref(), &location, warnings);
+
         if let Some(ref subitems) = item.item {
             // This is a folder
+            let (auth_type, auth_data) = parse_inheritable_auth(item.auth.as_ref());
             folders.push(ImportedFolder {
                 name: item.name.clone(),
                 parent_path: current_path.clone(),
+                auth_type,
+                auth_data,
+                headers: Vec::new(),
+                variables: Vec::new(),
+                pre_request_script,
+                test_script,
+                schemas: Vec::new(),
             });
 
             current_path.push(item.name.clone());
-            process_items(subitems, folders, requests, current_path)?;
+            process_items(subitems, folders, requests, warnings, current_path)?;
             current_path.pop();
         } else if let Some(ref request) = item.request {
             // This is a request
@@ -155,19 +336,7 @@ fn process_items(
                 PostmanUrl::Object(obj) => obj.raw.clone(),
             };
 
-            let headers = request
-                .header
-                .as_ref()
-                .map(|h| {
-                    h.iter()
-                        .map(|header| ImportedHeader {
-                            key: header.key.clone(),
-                            value: header.value.clone(),
-                            enabled: !header.disabled,
-                        })
-                        .collect()
-                })
-                .unwrap_or_default();
+            let headers = parse_headers(request.header.as_deref());
 
             let (body, body_type, form_data) = if let Some(ref body) = request.body {
                 match body.mode.as_str() {
@@ -216,11 +385,7 @@ fn process_items(
                 (String::new(), "none".to_string(), Vec::new())
             };
 
-            let (auth_type, auth_data) = if let Some(ref auth) = request.auth {
-                parse_auth(auth)
-            } else {
-                ("none".to_string(), "{}".to_string())
-            };
+            let (auth_type, auth_data) = parse_inheritable_auth(request.auth.as_ref());
 
             requests.push(ImportedRequest {
                 name: item.name.clone(),
@@ -233,6 +398,12 @@ fn process_items(
                 auth_data,
                 form_data,
                 folder_path: current_path.clone(),
+                variables: Vec::new(),
+                pre_request_script,
+                test_script,
+                assertions: Vec::new(),
+                extractions: Vec::new(),
+                response_schema: None,
             });
         }
     }
@@ -240,6 +411,75 @@ fn process_items(
     Ok(())
 }
 
+// Joins the `prerequest` and `test` event scripts, warning about APIs that
+// won't run locally
+fn parse_events(
+    events: Option<&[PostmanEvent]>,
+    location: &str,
+    warnings: &mut Vec<String>,
+) -> (String, String) {
+    let mut pre_request = Vec::new();
+    let mut test = Vec::new();
+
+    for event in events.unwrap_or_default().iter().filter(|e| !e.disabled) {
+        let source = match event.script.as_ref().and_then(|s| s.exec.as_ref()) {
+            Some(Value::Array(lines)) => lines
+                .iter()
+                .map(|line| line.as_str().unwrap_or_default())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Some(Value::String(source)) => source.clone(),
+            _ => continue,
+        };
+        if source.trim().is_empty() {
+            continue;
+        }
+
+        let apis = unsupported_apis(&source);
+        if !apis.is_empty() {
+            warnings.push(format!(
+                "{} ({} script) uses {}, not supported by fetchr scripts",
+                location,
+                event.listen,
+                apis.join(", ")
+            ));
+        }
+
+        match event.listen.as_str() {
+            "prerequest" => pre_request.push(source),
+            "test" => test.push(source),
+            other => warnings.push(format!(
+                "{} has an unsupported '{}' script, which was skipped",
+                location, other
+            )),
+        }
+    }
+
+    (pre_request.join("\n\n"), test.join("\n\n"))
+}
+
+fn parse_headers(headers: Option<&[PostmanHeader]>) -> Vec<ImportedHeader> {
+    headers
+        .map(|h| {
+            h.iter()
+                .map(|header| ImportedHeader {
+                    key: header.key.clone(),
+                    value: header.value.clone(),
+                    enabled: !header.disabled,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Postman treats a missing auth block as "inherit from parent"
+fn parse_inheritable_auth(auth: Option<&Value>) -> (String, String) {
+    match auth {
+        Some(auth) => parse_auth(auth),
+        None => ("inherit".to_string(), "{}".to_string()),
+    }
+}
+
 fn parse_auth(auth: &Value) -> (String, String) {
     if let Some(auth_type) = auth.get("type").and_then(|t| t.as_str()) {
         match auth_type {
@@ -316,3 +556,72 @@ fn parse_auth(auth: &Value) -> (String, String) {
 
     ("none".to_string(), "{}".to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(listen: &str, exec: Value) -> Value {
+        json!({"listen": listen, "script": {"type": "text/javascript", "exec": exec}})
+    }
+
+    #[test]
+    fn imports_scripts_and_warns_about_unsupported_apis() {
+        let collection = json!({
+            "info": {"name": "API"},
+            "event": [
+                event("prerequest", json!(["pm.variables.set('id', 1);", "console.log('start');"])),
+                event("test", json!("")),
+            ],
+            "item": [{
+                "name": "Users",
+                "event": [event("test", json!("pm.test('ok', () => {});"))],
+                "item": [{
+                    "name": "Get user",
+                    "event": [
+                        event("prerequest", json!("pm.sendRequest('https://auth', () => {});")),
+                        event("test", json!("tests['ok'] = responseCode.code === 200;")),
+                        event("test", json!("pm.expect(1).to.eql(1);")),
+                        {"listen": "test", "disabled": true, "script": {"exec": "CryptoJS.MD5('x');"}},
+                        event("beforeRun", json!("postman.setEnvironmentVariable('a', 1);")),
+                    ],
+                    "request": {"method": "GET", "url": "{{host}}/users/1"},
+                }],
+            }],
+        });
+
+        let imported = parse_postman_collection(&collection.to_string()).unwrap();
+        assert_eq!(
+            imported.pre_request_script,
+            "pm.variables.set('id', 1);\nconsole.log('start');"
+        );
+        assert_eq!(imported.test_script, "");
+        assert_eq!(imported.folders[0].test_script, "pm.test('ok', () => {});");
+        let request = &imported.requests[0];
+        assert_eq!(
+            request.pre_request_script,
+            "pm.sendRequest('https://auth', () => {});"
+        );
+        assert_eq!(
+            request.test_script,
+            "tests['ok'] = responseCode.code === 200;\n\npm.expect(1).to.eql(1);"
+        );
+        assert_eq!(
+            imported.warnings,
+            [
+                "Users/Get user (prerequest script) uses pm.sendRequest, not supported by fetchr scripts",
+                "Users/Get user (test script) uses tests[], not supported by fetchr scripts",
+                "Users/Get user (beforeRun script) uses postman.*, not supported by fetchr scripts",
+                "Users/Get user has an unsupported 'beforeRun' script, which was skipped",
+            ]
+        );
+
+        let error = parse_postman_collection(r#"{"info": {}}"#).unwrap_err();
+        assert!(
+            error.starts_with("Invalid Postman collection: "),
+            "{}",
+            error
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use crate::http_client::{AuthData, HttpRequest, KeyValue};
+use crate::secrets::{map_auth_secrets, map_variable_secrets, MASK};
+
+// Headers whose values are credentials regardless of content
+pub const SENSITIVE_HEADERS: [&str; 6] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+    "api-key",
+];
+
+pub const REDACTION_SETTING: &str = "redact_secrets";
+
+pub fn is_sensitive_header(name: &str) -> bool {
+    SENSITIVE_HEADERS
+        .iter()
+        .any(|h| h.eq_ignore_ascii_case(name.trim()))
+}
+
+// Masks sensitive headers, credential auth fields and secret variable values
+// in anything that leaves the request editor (history, exports, snippets)
+pub struct Redactor {
+    enabled: bool,
+    secrets: Vec<String>,
+}
+
+impl Redactor {
+    pub fn new(enabled: bool, mut secrets: Vec<String>) -> Self {
+        // Longest first so a secret containing another is masked whole
+        secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+        secrets.dedup();
+        Redactor { enabled, secrets }
+    }
+
+    pub fn text(&self, text: &str) -> String {
+        if !self.enabled {
+            return text.to_string();
+        }
+        let mut result = text.to_string();
+        for secret in &self.secrets {
+            result = result.replace(secret.as_str(), MASK);
+        }
+        result
+    }
+
+    pub fn header_value(&self, name: &str, value: &str) -> String {
+        if self.enabled && is_sensitive_header(name) && !value.is_empty() {
+            MASK.to_string()
+        } else {
+            self.text(value)
+        }
+    }
+
+    pub fn headers(&self, headers: &[KeyValue]) -> Vec<KeyValue> {
+        headers
+            .iter()
+            .map(|h| KeyValue {
+                key: h.key.clone(),
+                value: self.header_value(&h.key, &h.value),
+                enabled: h.enabled,
+            })
+            .collect()
+    }
+
+    // Redacts a headers JSON string as stored on requests and collections
+    pub fn headers_json(&self, headers: &str) -> String {
+        match serde_json::from_str::<Vec<KeyValue>>(headers) {
+            Ok(parsed) => serde_json::to_string(&self.headers(&parsed)).unwrap_or_default(),
+            Err(_) => self.text(headers),
+        }
+    }
+
+    // Redacts an auth_data JSON string as stored on requests and collections
+    pub fn auth_data_json(&self, auth_data: &str) -> String {
+        if !self.enabled {
+            return auth_data.to_string();
+        }
+        map_auth_secrets(auth_data, |v| Ok(mask_non_empty(v))).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    // Redacts a variables JSON string, masking the values marked secret
+    pub fn variables_json(&self, variables: &str) -> String {
+        if !self.enabled {
+            return variables.to_string();
+        }
+        map_variable_secrets(variables, |v| Ok(mask_non_empty(v)))
+            .unwrap_or_else(|_| "[]".to_string())
+    }
+
+    pub fn auth_data(&self, auth: &AuthData) -> AuthData {
+        if !self.enabled {
+            return auth.clone();
+        }
+        AuthData {
+            password: auth.password.as_deref().map(mask_non_empty),
+            token: auth.token.as_deref().map(mask_non_empty),
+            value_field: auth.value_field.as_deref().map(mask_non_empty),
+            secret: auth.secret.as_deref().map(mask_non_empty),
+            ..auth.clone()
+        }
+    }
+
+    pub fn request(&self, request: &HttpRequest) -> HttpRequest {
+        let mut redacted = request.clone();
+        redacted.url = self.text(&request.url);
+        redacted.headers = self.headers(&request.headers);
+        redacted.body = self.text(&request.body);
+        redacted.auth_data = self.auth_data(&request.auth_data);
+        if let Some(fields) = redacted.form_data.as_mut() {
+            for field in fields.iter_mut() {
+                field.value = self.text(&field.value);
+            }
+        }
+        redacted
+    }
+}
+
+// Values that only reference variables, e.g. `{{token}}`, are kept: the
+// variable is masked wherever its own value is exported
+fn mask_non_empty(value: &str) -> String {
+    if value.is_empty() || is_placeholder_only(value) {
+        value.to_string()
+    } else {
+        MASK.to_string()
+    }
+}
+
+fn is_placeholder_only(value: &str) -> bool {
+    let mut rest = value.trim();
+    if rest.is_empty() {
+        return false;
+    }
+    while let Some(after_open) = rest.strip_prefix("{{") {
+        let Some(end) = after_open.find("}}") else {
+            return false;
+        };
+        let name = after_open[..end].trim();
+        if name.is_empty() || name.contains('{') {
+            return false;
+        }
+        rest = after_open[end + 2..].trim_start();
+    }
+    rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn auth_json(redactor: &Redactor, auth_data: Value) -> Value {
+        serde_json::from_str(&redactor.auth_data_json(&auth_data.to_string())).unwrap()
+    }
+
+    #[test]
+    fn masks_literal_auth_secrets() {
+        let redactor = Redactor::new(true, Vec::new());
+        let redacted = auth_json(
+            &redactor,
+            json!({"username": "alice", "password": "hunter2", "token": "abc {{suffix}}"}),
+        );
+        assert_eq!(
+            redacted,
+            json!({"username": "alice", "password": MASK, "token": MASK})
+        );
+    }
+
+    #[test]
+    fn keeps_placeholder_auth_values() {
+        let redactor = Redactor::new(true, Vec::new());
+        let redacted = auth_json(
+            &redactor,
+            json!({"password": "{{password}}", "token": " {{ prefix }}{{$base64 user}} ", "secret": ""}),
+        );
+        assert_eq!(
+            redacted,
+            json!({"password": "{{password}}", "token": " {{ prefix }}{{$base64 user}} ", "secret": ""})
+        );
+
+        let auth: AuthData = serde_json::from_value(json!({
+            "token": "{{token}}",
+            "password": "{{}}",
+        }))
+        .unwrap();
+        let redacted = redactor.auth_data(&auth);
+        assert_eq!(redacted.token.as_deref(), Some("{{token}}"));
+        assert_eq!(redacted.password.as_deref(), Some(MASK));
+    }
+
+    #[test]
+    fn leaves_non_secret_fields_alone() {
+        let redactor = Redactor::new(true, vec!["s3cret".to_string()]);
+        let redacted = auth_json(
+            &redactor,
+            json!({"username": "s3cret", "key": "X-Api-Key", "algorithm": "sha256"}),
+        );
+        assert_eq!(
+            redacted,
+            json!({"username": "s3cret", "key": "X-Api-Key", "algorithm": "sha256"})
+        );
+
+        let variables = json!([
+            {"key": "host", "value": "example.com"},
+            {"key": "token", "value": "t0ken", "secret": true},
+            {"key": "alias", "value": "{{token}}", "secret": true},
+        ]);
+        let redacted: Value =
+            serde_json::from_str(&redactor.variables_json(&variables.to_string())).unwrap();
+        assert_eq!(redacted[0]["value"], "example.com");
+        assert_eq!(redacted[1]["value"], MASK);
+        assert_eq!(redacted[2]["value"], "{{token}}");
+    }
+
+    #[test]
+    fn does_nothing_when_disabled() {
+        let redactor = Redactor::new(false, vec!["s3cret".to_string()]);
+        let auth_data = json!({"password": "hunter2"});
+        assert_eq!(auth_json(&redactor, auth_data.clone()), auth_data);
+        assert_eq!(redactor.text("s3cret"), "s3cret");
+        assert_eq!(
+            redactor.header_value("Authorization", "Bearer x"),
+            "Bearer x"
+        );
+    }
+}
@@ -0,0 +1,326 @@
+use crate::http_client::KeyValue;
+use crate::runner::{RequestRunResult, RunSummary};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Junit,
+    Json,
+    Html,
+}
+
+pub fn render_report(summary: &RunSummary, format: ReportFormat) -> Result<String, String> {
+    match format {
+        ReportFormat::Junit => Ok(junit(summary)),
+        ReportFormat::Json => serde_json::to_string_pretty(summary).map_err(|e| e.to_string()),
+        ReportFormat::Html => Ok(html(summary)),
+    }
+}
+
+// One test suite per iteration and one test case per request. Requests that
+// could not be sent are errors; failed checks are failures.
+fn junit(summary: &RunSummary) -> String {
+    let errors = summary.results.iter().filter(|r| r.error.is_some()).count();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{}\">\n",
+        escape(&summary.name),
+        summary.total,
+        summary.failed - errors,
+        errors,
+        seconds(summary.duration),
+    ));
+
+    for iteration in 0..summary.iterations {
+        let results: Vec<&RequestRunResult> = summary
+            .results
+            .iter()
+            .filter(|r| r.iteration == iteration)
+            .collect();
+        let name = if summary.iterations > 1 {
+            format!("{} (iteration {})", summary.name, iteration + 1)
+        } else {
+            summary.name.clone()
+        };
+        let errors = results.iter().filter(|r| r.error.is_some()).count();
+        let failures = results.iter().filter(|r| !r.passed).count() - errors;
+        let time: u128 = results.iter().map(|r| r.response_time).sum();
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{}\" timestamp=\"{}\">\n",
+            escape(&name),
+            results.len(),
+            failures,
+            errors,
+            seconds(time),
+            escape(&summary.started_at),
+        ));
+
+        for result in results {
+            let classname = if result.folder.is_empty() {
+                summary.name.clone()
+            } else {
+                format!("{}.{}", summary.name, result.folder.replace('/', "."))
+            };
+            xml.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\"",
+                escape(&result.name),
+                escape(&classname),
+                seconds(result.response_time),
+            ));
+            let failures = result.failures();
+            if result.passed {
+                xml.push_str("/>\n");
+                continue;
+            }
+            let tag = if result.error.is_some() {
+                "error"
+            } else {
+                "failure"
+            };
+            xml.push_str(&format!(
+                ">\n      <{} message=\"{}\">{}</{}>\n    </testcase>\n",
+                tag,
+                escape(
+                    failures
+                        .first()
+                        .map(String::as_str)
+                        .unwrap_or("Request failed")
+                ),
+                escape(&failures.join("\n")),
+                tag,
+            ));
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2rem;color:#1f2937}\
+table{border-collapse:collapse;width:100%}th,td{text-align:left;padding:.4rem .6rem;border-bottom:1px solid #e5e7eb}\
+.passed{color:#16a34a}.failed{color:#dc2626}.summary span{margin-right:1.5rem}\
+.failure{border:1px solid #fecaca;border-radius:6px;padding:.5rem 1rem;margin:1rem 0}\
+pre{background:#f3f4f6;padding:.5rem;overflow:auto;max-height:30rem;white-space:pre-wrap}code{font-size:.9em}";
+
+// A single file with no external assets, so it can be attached to CI runs
+fn html(summary: &RunSummary) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{} - fetchr run</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape(&summary.name),
+        HTML_STYLE,
+    );
+    html.push_str(&format!(
+        "<h1>{}</h1>\n<p class=\"summary\"><span class=\"passed\">{} passed</span><span class=\"{}\">{} failed</span><span>{} requests, {} iteration(s)</span><span>{} ms</span><span>{}</span></p>\n",
+        escape(&summary.name),
+        summary.passed,
+        if summary.failed > 0 { "failed" } else { "" },
+        summary.failed,
+        summary.total,
+        summary.iterations,
+        summary.duration,
+        escape(&summary.started_at),
+    ));
+
+    html.push_str("<table>\n<tr><th></th><th>Iteration</th><th>Request</th><th>Status</th><th>Time</th></tr>\n");
+    for (index, result) in summary.results.iter().enumerate() {
+        let outcome = if result.passed {
+            "<span class=\"passed\">✓</span>".to_string()
+        } else {
+            format!("<a class=\"failed\" href=\"#failure-{}\">✗</a>", index)
+        };
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td><code>{}</code> {}</td><td>{}</td><td>{} ms</td></tr>\n",
+            outcome,
+            result.iteration + 1,
+            escape(&result.method),
+            escape(&request_path(result)),
+            result
+                .status
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| "Error".to_string()),
+            result.response_time,
+        ));
+    }
+    html.push_str("</table>\n");
+
+    if summary.failed > 0 {
+        html.push_str("<h2>Failures</h2>\n");
+    }
+    for (index, result) in summary
+        .results
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| !r.passed)
+    {
+        html.push_str(&format!(
+            "<div class=\"failure\" id=\"failure-{}\">\n<h3><code>{}</code> {} <small>(iteration {})</small></h3>\n<ul>\n",
+            index,
+            escape(&result.method),
+            escape(&request_path(result)),
+            result.iteration + 1,
+        ));
+        for failure in result.failures() {
+            html.push_str(&format!("<li class=\"failed\">{}</li>\n", escape(&failure)));
+        }
+        html.push_str("</ul>\n");
+
+        if let Some(details) = &result.details {
+            html.push_str(&format!(
+                "<details open>\n<summary>Request</summary>\n<pre>{} {}\n{}\n\n{}</pre>\n</details>\n",
+                escape(&result.method),
+                escape(&details.url),
+                escape(&header_lines(&details.request_headers)),
+                escape(&details.request_body),
+            ));
+            html.push_str(&format!(
+                "<details open>\n<summary>Response</summary>\n<pre>{} {}\n{}\n\n{}</pre>\n</details>\n",
+                result.status.unwrap_or_default(),
+                escape(&details.status_text),
+                escape(&header_lines(&details.response_headers)),
+                escape(&details.response_body),
+            ));
+        }
+        html.push_str("</div>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn request_path(result: &RequestRunResult) -> String {
+    if result.folder.is_empty() {
+        result.name.clone()
+    } else {
+        format!("{}/{}", result.folder, result.name)
+    }
+}
+
+fn header_lines(headers: &[KeyValue]) -> String {
+    headers
+        .iter()
+        .map(|h| format!("{}: {}", h.key, h.value))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn seconds(millis: u128) -> String {
+    format!("{:.3}", millis as f64 / 1000.0)
+}
+
+// Escapes text for XML and HTML, dropping control characters XML 1.0 forbids
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if (c as u32) < 0x20 => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn result(name: &str, passed: bool, error: Option<&str>) -> serde_json::Value {
+        json!({
+            "iteration": 0,
+            "request_id": name,
+            "name": name,
+            "folder": "Users",
+            "method": "GET",
+            "url": "https://api.example.com",
+            "status": error.is_none().then_some(200),
+            "response_time": 40,
+            "passed": passed,
+            "error": error,
+            "assertion_results": [],
+            "test_results": [],
+            "schema_validation": null,
+            "details": null,
+        })
+    }
+
+    fn summary() -> RunSummary {
+        let mut failed = result("Create <user>", false, None);
+        failed["assertion_results"] = json!([{
+            "name": "Status & body",
+            "assertion": {"source": "status", "operator": "equals", "value": "201"},
+            "passed": false,
+            "actual": "200",
+            "message": "expected <201>\u{1} got 200",
+        }]);
+        serde_json::from_value(json!({
+            "collection_id": "api",
+            "name": "Tom & Jerry's <API>",
+            "started_at": "2026-01-01T00:00:00Z",
+            "duration": 1500,
+            "iterations": 1,
+            "total": 3,
+            "passed": 1,
+            "failed": 2,
+            "results": [
+                result("List users", true, None),
+                failed,
+                result("Delete user", false, Some("connection refused")),
+            ],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn junit_counts_failures_and_errors_separately() {
+        let xml = render_report(&summary(), ReportFormat::Junit).unwrap();
+        assert!(xml.contains(
+            "<testsuites name=\"Tom &amp; Jerry&#39;s &lt;API&gt;\" tests=\"3\" failures=\"1\" errors=\"1\""
+        ));
+        assert!(xml.contains("tests=\"3\" failures=\"1\" errors=\"1\" time=\"0.120\""));
+        assert!(xml.contains("<testcase name=\"List users\""));
+        assert!(
+            xml.contains("<failure message=\"Status &amp; body: expected &lt;201&gt; got 200\">")
+        );
+        assert!(xml.contains("<error message=\"connection refused\">"));
+        assert!(xml.contains("classname=\"Tom &amp; Jerry&#39;s &lt;API&gt;.Users\""));
+        assert!(!xml.contains('\u{1}'));
+        assert!(!xml.contains("<user>"));
+    }
+
+    #[test]
+    fn json_round_trips_the_summary() {
+        let report = render_report(&summary(), ReportFormat::Json).unwrap();
+        let parsed: RunSummary = serde_json::from_str(&report).unwrap();
+        assert_eq!((parsed.total, parsed.passed, parsed.failed), (3, 1, 2));
+        assert_eq!(
+            parsed.results[1].failures(),
+            ["Status & body: expected <201>\u{1} got 200"]
+        );
+        assert_eq!(
+            parsed.results[2].error.as_deref(),
+            Some("connection refused")
+        );
+    }
+
+    #[test]
+    fn html_escapes_names_and_failures() {
+        let html = render_report(&summary(), ReportFormat::Html).unwrap();
+        assert!(html.contains("<title>Tom &amp; Jerry&#39;s &lt;API&gt; - fetchr run</title>"));
+        assert!(html.contains("<span class=\"passed\">1 passed</span>"));
+        assert!(html.contains("<span class=\"failed\">2 failed</span>"));
+        assert!(html.contains("Users/Create &lt;user&gt;"));
+        assert!(html
+            .contains("<li class=\"failed\">Status &amp; body: expected &lt;201&gt; got 200</li>"));
+        assert!(html.contains("<li class=\"failed\">connection refused</li>"));
+        assert!(!html.contains('\u{1}'));
+        assert!(!html.contains("<user>"));
+    }
+}
@@ -0,0 +1,638 @@
+use crate::assertions::AssertionResult;
+use crate::db::{Collection, Database, Request};
+use crate::http_client::{HttpRequest, HttpResponse, KeyValue};
+use crate::json_schema::SchemaValidation;
+use crate::scripting::TestResult;
+use crate::{execute_request, send_redactor, AppState};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+// Longest response body kept in a failed request's details
+const DETAIL_BODY_LIMIT: usize = 64 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RequestRunResult {
+    // Zero-based data file row; always 0 without a data file
+    pub iteration: usize,
+    pub request_id: String,
+    pub name: String,
+    // Folders between the run's root and the request, e.g. "Users/Admin"
+    pub folder: String,
+    pub method: String,
+    pub url: String,
+    pub status: Option<u16>,
+    pub response_time: u128,
+    // Sent, with every assertion, test and schema check passing
+    pub passed: bool,
+    // Set when the request could not be prepared or sent
+    pub error: Option<String>,
+    pub assertion_results: Vec<AssertionResult>,
+    pub test_results: Vec<TestResult>,
+    pub schema_validation: Option<SchemaValidation>,
+    // What was sent and received, kept for failed requests only
+    pub details: Option<FailureDetails>,
+}
+
+// The resolved request and its response, with secrets masked as in exports
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FailureDetails {
+    pub url: String,
+    pub request_headers: Vec<KeyValue>,
+    pub request_body: String,
+    pub status_text: String,
+    pub response_headers: Vec<KeyValue>,
+    pub response_body: String,
+}
+
+impl RequestRunResult {
+    // Why the request failed: its error, or each failing check
+    pub fn failures(&self) -> Vec<String> {
+        match &self.error {
+            Some(error) => vec![error.clone()],
+            None => check_failures(
+                &self.assertion_results,
+                &self.test_results,
+                self.schema_validation.as_ref(),
+            ),
+        }
+    }
+}
+
+// Describes each failing assertion, test and schema violation of a response
+pub fn check_failures(
+    assertion_results: &[AssertionResult],
+    test_results: &[TestResult],
+    schema_validation: Option<&SchemaValidation>,
+) -> Vec<String> {
+    let mut failures: Vec<String> = assertion_results
+        .iter()
+        .filter(|a| !a.passed)
+        .map(|a| match &a.message {
+            Some(message) => format!("{}: {}", a.name, message),
+            None => a.name.clone(),
+        })
+        .collect();
+    failures.extend(
+        test_results
+            .iter()
+            .filter(|t| !t.passed)
+            .map(|t| match &t.error {
+                Some(error) => format!("{}: {}", t.name, error),
+                None => t.name.clone(),
+            }),
+    );
+    if let Some(schema) = schema_validation.filter(|s| !s.valid) {
+        match &schema.error {
+            Some(error) => failures.push(error.clone()),
+            None => failures.extend(schema.violations.iter().map(|v| {
+                let pointer = if v.pointer.is_empty() {
+                    "/"
+                } else {
+                    &v.pointer
+                };
+                format!("Schema {}: {}", pointer, v.message)
+            })),
+        }
+    }
+    failures
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RunSummary {
+    pub collection_id: String,
+    pub name: String,
+    pub started_at: String,
+    // Wall-clock time of the whole run in milliseconds
+    pub duration: u128,
+    pub iterations: usize,
+    // Requests sent across all iterations
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub results: Vec<RequestRunResult>,
+}
+
+// Progress of a run, reported as each request starts and finishes
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RunEvent {
+    Started {
+        collection_id: String,
+        iterations: usize,
+        total: usize,
+    },
+    RequestStarted {
+        index: usize,
+        iteration: usize,
+        name: String,
+        folder: String,
+    },
+    RequestFinished {
+        index: usize,
+        result: Box<RequestRunResult>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    // Environments to resolve against; the active environment when None
+    pub environment_ids: Option<Vec<String>>,
+    // One pass over the requests per data row
+    pub iterations: Vec<Vec<KeyValue>>,
+    // Command-line `--var` values, overriding every other variable
+    pub overrides: Vec<KeyValue>,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        RunOptions {
+            environment_ids: None,
+            iterations: vec![Vec::new()],
+            overrides: Vec::new(),
+        }
+    }
+}
+
+struct RunStep {
+    request: Request,
+    folder: String,
+}
+
+// Sends every request under a collection or folder, one at a time, so values
+// extracted or set by scripts are visible to the requests after them. The
+// requests run once per data row, with the row's values as variables.
+pub async fn run_collection<F: FnMut(RunEvent)>(
+    state: &AppState,
+    collection_id: &str,
+    options: &RunOptions,
+    mut on_progress: F,
+) -> Result<RunSummary, String> {
+    let iterations = &options.iterations;
+    let started_at = chrono::Utc::now().to_rfc3339();
+    let start = Instant::now();
+    let (root, steps) = {
+        let db = state.db.lock().unwrap();
+        collect_steps(&db, collection_id)?
+    };
+    on_progress(RunEvent::Started {
+        collection_id: root.id.clone(),
+        iterations: iterations.len(),
+        total: steps.len() * iterations.len(),
+    });
+
+    let mut results = Vec::new();
+    for (iteration, data) in iterations.iter().enumerate() {
+        for step in &steps {
+            let index = results.len();
+            on_progress(RunEvent::RequestStarted {
+                index,
+                iteration,
+                name: step.request.name.clone(),
+                folder: step.folder.clone(),
+            });
+            let result = run_step(state, step, iteration, data, options).await;
+            on_progress(RunEvent::RequestFinished {
+                index,
+                result: Box::new(result.clone()),
+            });
+            results.push(result);
+        }
+    }
+
+    let passed = results.iter().filter(|r| r.passed).count();
+    Ok(RunSummary {
+        collection_id: root.id,
+        name: root.name,
+        started_at,
+        duration: start.elapsed().as_millis(),
+        iterations: iterations.len(),
+        total: results.len(),
+        passed,
+        failed: results.len() - passed,
+        results,
+    })
+}
+
+async fn run_step(
+    state: &AppState,
+    step: &RunStep,
+    iteration: usize,
+    data: &[KeyValue],
+    options: &RunOptions,
+) -> RequestRunResult {
+    let RunStep { request, folder } = step;
+    let mut result = RequestRunResult {
+        iteration,
+        request_id: request.id.clone(),
+        name: request.name.clone(),
+        folder: folder.clone(),
+        method: request.method.clone(),
+        url: request.url.clone(),
+        status: None,
+        response_time: 0,
+        passed: false,
+        error: None,
+        assertion_results: Vec::new(),
+        test_results: Vec::new(),
+        schema_validation: None,
+        details: None,
+    };
+
+    let response = match http_request(request) {
+        Ok(mut http_request) => {
+            http_request.environment_ids = options.environment_ids.clone();
+            http_request.iteration_data = data.to_vec();
+            http_request
+                .variables
+                .extend(options.overrides.iter().cloned());
+            execute_request(state, http_request).await
+        }
+        Err(e) => Err(e),
+    };
+    match response {
+        Ok(response) => {
+            result.passed = response.assertion_results.iter().all(|a| a.passed)
+                && response.test_results.iter().all(|t| t.passed)
+                && response.schema_validation.as_ref().is_none_or(|s| s.valid);
+            if !result.passed {
+                result.details =
+                    failure_details(state, &request.collection_id, options, &response).ok();
+            }
+            result.status = Some(response.status);
+            result.response_time = response.response_time;
+            result.assertion_results = response.assertion_results;
+            result.test_results = response.test_results;
+            result.schema_validation = response.schema_validation;
+        }
+        Err(e) => result.error = Some(e),
+    }
+    result
+}
+
+// Masks the secrets of the request's collection chain and of the environments
+// the run resolves against
+fn failure_details(
+    state: &AppState,
+    collection_id: &str,
+    options: &RunOptions,
+    response: &HttpResponse,
+) -> Result<FailureDetails, String> {
+    let redactor = {
+        let db = state.db.lock().unwrap();
+        let vault = state.vault.lock().unwrap();
+        send_redactor(
+            &db,
+            &vault,
+            Some(collection_id),
+            options.environment_ids.as_deref(),
+        )?
+    };
+    let request = response
+        .resolved_request
+        .as_ref()
+        .map(|r| redactor.request(r))
+        .ok_or_else(|| "Response has no resolved request".to_string())?;
+
+    let mut response_headers: Vec<KeyValue> = response
+        .headers
+        .iter()
+        .map(|(key, value)| KeyValue {
+            key: key.clone(),
+            value: value.clone(),
+            enabled: true,
+        })
+        .collect();
+    response_headers.sort_by(|a, b| a.key.cmp(&b.key));
+
+    let mut response_body = redactor.text(&response.body);
+    if response_body.len() > DETAIL_BODY_LIMIT {
+        let mut end = DETAIL_BODY_LIMIT;
+        while !response_body.is_char_boundary(end) {
+            end -= 1;
+        }
+        response_body.truncate(end);
+        response_body.push_str("\n… (truncated)");
+    }
+
+    Ok(FailureDetails {
+        url: request.url,
+        request_headers: request.headers.into_iter().filter(|h| h.enabled).collect(),
+        request_body: request.body,
+        status_text: response.status_text.clone(),
+        response_headers: redactor.headers(&response_headers),
+        response_body,
+    })
+}
+
+// Lists the requests to run in sidebar order: each folder's subfolders
+// first, then its own requests, oldest first
+fn collect_steps(db: &Database, collection_id: &str) -> Result<(Collection, Vec<RunStep>), String> {
+    let collections = db.get_all_collections().map_err(|e| e.to_string())?;
+    let root = collections
+        .iter()
+        .find(|c| c.id == collection_id)
+        .cloned()
+        .ok_or_else(|| "Collection not found".to_string())?;
+    let mut steps = Vec::new();
+    walk(db, &collections, &root, "", &mut steps)?;
+    Ok((root, steps))
+}
+
+fn walk(
+    db: &Database,
+    collections: &[Collection],
+    folder: &Collection,
+    path: &str,
+    steps: &mut Vec<RunStep>,
+) -> Result<(), String> {
+    for child in collections
+        .iter()
+        .filter(|c| c.parent_id.as_deref() == Some(folder.id.as_str()))
+    {
+        let child_path = if path.is_empty() {
+            child.name.clone()
+        } else {
+            format!("{}/{}", path, child.name)
+        };
+        walk(db, collections, child, &child_path, steps)?;
+    }
+    let requests = db
+        .get_requests_by_collection(&folder.id)
+        .map_err(|e| e.to_string())?;
+    steps.extend(requests.into_iter().map(|request| RunStep {
+        request,
+        folder: path.to_string(),
+    }));
+    Ok(())
+}
+
+// Builds what the request builder would send for a saved request. Callers set
+// the environments and data row to resolve against; without them it uses the
+// active environment and no data row.
+pub fn http_request(request: &Request) -> Result<HttpRequest, String> {
+    let response_schema = if request.response_schema.trim().is_empty() {
+        None
+    } else {
+        Some(
+            serde_json::from_str(&request.response_schema)
+                .map_err(|e| format!("Invalid response schema: {}", e))?,
+        )
+    };
+    Ok(HttpRequest {
+        method: request.method.clone(),
+        url: request.url.clone(),
+        headers: parse(&request.headers, "headers")?,
+        body: request.body.clone(),
+        body_type: request.body_type.clone(),
+        auth_type: request.auth_type.clone(),
+        auth_data: parse(&request.auth_data, "auth data")?,
+        form_data: None,
+        collection_id: Some(request.collection_id.clone()),
+        strict_variables: false,
+        variables: parse(&request.variables, "variables")?,
+        environment_ids: None,
+        pre_request_script: request.pre_request_script.clone(),
+        test_script: request.test_script.clone(),
+        assertions: parse(&request.assertions, "assertions")?,
+        extractions: parse(&request.extractions, "extraction rules")?,
+        response_schema,
+        iteration_data: Vec::new(),
+        name: request.name.clone(),
+    })
+}
+
+fn parse<T: DeserializeOwned + Default>(json: &str, field: &str) -> Result<T, String> {
+    if json.trim().is_empty() {
+        return Ok(T::default());
+    }
+    serde_json::from_str(json).map_err(|e| format!("Invalid {}: {}", field, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Environment;
+    use crate::report::{render_report, ReportFormat};
+    use crate::secrets::Vault;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    fn environment(id: &str, token: &str) -> Environment {
+        Environment {
+            id: id.to_string(),
+            name: id.to_string(),
+            variables: json!([{"key": "token", "value": token, "secret": true}]).to_string(),
+            is_active: id == "dev",
+            created_at: String::new(),
+            dotenv_path: None,
+            parent_id: None,
+        }
+    }
+
+    fn request(
+        id: &str,
+        collection_id: &str,
+        created_at: &str,
+        extra: serde_json::Value,
+    ) -> Request {
+        let mut fields = json!({
+            "id": id,
+            "collection_id": collection_id,
+            "name": id,
+            "method": "GET",
+            "url": "{{host}}/",
+            "headers": "[]",
+            "body": "",
+            "body_type": "none",
+            "auth_type": "none",
+            "auth_data": "{}",
+            "created_at": created_at,
+            "updated_at": created_at,
+        });
+        fields
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(fields).unwrap()
+    }
+
+    // Answers every connection with an empty 200 response
+    fn serve_ok() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                let mut buffer = [0; 4096];
+                let _ = std::io::Read::read(&mut stream, &mut buffer);
+                let _ = std::io::Write::write_all(
+                    &mut stream,
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                );
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    #[tokio::test]
+    async fn runs_folders_in_order_and_aggregates_results() {
+        let db = Database::new(":memory:").unwrap();
+        for (id, parent_id) in [("api", None), ("users", Some("api"))] {
+            db.create_collection(
+                &serde_json::from_value(json!({
+                    "id": id,
+                    "name": id,
+                    "parent_id": parent_id,
+                    "is_folder": parent_id.is_some(),
+                    "created_at": "",
+                }))
+                .unwrap(),
+            )
+            .unwrap();
+        }
+        let created =
+            json!([{"enabled": true, "source": "status", "operator": "equals", "value": "201"}]);
+        db.save_request(&request(
+            "list",
+            "api",
+            "1",
+            json!({"assertions": created.to_string()}),
+        ))
+        .unwrap();
+        db.save_request(&request(
+            "broken",
+            "api",
+            "2",
+            json!({"headers": "not json"}),
+        ))
+        .unwrap();
+        db.save_request(&request("get", "users", "3", json!({})))
+            .unwrap();
+        let state = AppState {
+            db: Mutex::new(db),
+            vault: Mutex::new(Vault::default()),
+        };
+        let host = KeyValue {
+            key: "host".to_string(),
+            value: serve_ok(),
+            enabled: true,
+        };
+        let options = RunOptions {
+            iterations: vec![Vec::new(), Vec::new()],
+            overrides: vec![host],
+            ..RunOptions::default()
+        };
+
+        let mut events = Vec::new();
+        let summary = run_collection(&state, "api", &options, |event| events.push(event))
+            .await
+            .unwrap();
+        let order: Vec<(usize, &str, &str)> = summary
+            .results
+            .iter()
+            .map(|r| (r.iteration, r.folder.as_str(), r.name.as_str()))
+            .collect();
+        assert_eq!(
+            order,
+            [
+                (0, "users", "get"),
+                (0, "", "list"),
+                (0, "", "broken"),
+                (1, "users", "get"),
+                (1, "", "list"),
+                (1, "", "broken"),
+            ]
+        );
+        assert_eq!((summary.total, summary.passed, summary.failed), (6, 2, 4));
+        assert!(summary.results[0].passed);
+        assert_eq!(summary.results[1].status, Some(200));
+        assert!(summary.results[1].error.is_none());
+        assert!(summary.results[2]
+            .error
+            .as_deref()
+            .unwrap()
+            .starts_with("Invalid headers"));
+        assert!(matches!(events[0], RunEvent::Started { total: 6, .. }));
+        assert_eq!(events.len(), 13);
+
+        let missing = run_collection(&state, "missing", &options, |_| {}).await;
+        assert_eq!(missing.unwrap_err(), "Collection not found");
+    }
+
+    #[test]
+    fn failure_details_mask_the_run_environments_secrets() {
+        let db = Database::new(":memory:").unwrap();
+        db.save_environment(&environment("dev", "dev-token"))
+            .unwrap();
+        db.save_environment(&environment("staging", "staging-token"))
+            .unwrap();
+        let state = AppState {
+            db: Mutex::new(db),
+            vault: Mutex::new(Vault::default()),
+        };
+        let response: HttpResponse = serde_json::from_value(json!({
+            "status": 401,
+            "status_text": "Unauthorized",
+            "headers": {"www-authenticate": "Bearer staging-token"},
+            "body": "{\"error\": \"staging-token expired\"}",
+            "response_time": 12,
+            "size": 0,
+            "cookies": [],
+            "resolved_request": {
+                "method": "GET",
+                "url": "https://staging.example.com/?token=staging-token",
+                "headers": [{"key": "Authorization", "value": "Bearer staging-token", "enabled": true}],
+                "body": "",
+                "body_type": "none",
+                "auth_type": "none",
+                "auth_data": {},
+                "form_data": null,
+                "collection_id": null,
+            },
+        }))
+        .unwrap();
+        let options = RunOptions {
+            environment_ids: Some(vec!["staging".to_string()]),
+            ..RunOptions::default()
+        };
+
+        let details = failure_details(&state, "missing", &options, &response).unwrap();
+        let summary = RunSummary {
+            collection_id: "api".to_string(),
+            name: "API".to_string(),
+            started_at: String::new(),
+            duration: 12,
+            iterations: 1,
+            total: 1,
+            passed: 0,
+            failed: 1,
+            results: vec![RequestRunResult {
+                iteration: 0,
+                request_id: "r1".to_string(),
+                name: "Me".to_string(),
+                folder: String::new(),
+                method: "GET".to_string(),
+                url: "{{host}}/".to_string(),
+                status: Some(401),
+                response_time: 12,
+                passed: false,
+                error: None,
+                assertion_results: Vec::new(),
+                test_results: Vec::new(),
+                schema_validation: None,
+                details: Some(details),
+            }],
+        };
+        for format in [ReportFormat::Json, ReportFormat::Html] {
+            let report = render_report(&summary, format).unwrap();
+            assert!(report.contains("Bearer ********"), "{:?}", format);
+            assert!(!report.contains("staging-token"), "{:?}", format);
+        }
+
+        // Without the run's selection only the active environment is masked
+        let details =
+            failure_details(&state, "missing", &RunOptions::default(), &response).unwrap();
+        assert!(details.url.contains("staging-token"));
+    }
+}
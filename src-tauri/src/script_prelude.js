@@ -0,0 +1,337 @@
+// Postman-style `pm` API for request scripts. Evaluated before every script
+// with `__input` defined; the host reads the result back through `__output()`.
+(function (global) {
+  const input = global.__input;
+  const logs = [];
+  const tests = [];
+  const updates = [];
+
+  // Variables visible to the script, by scope, including those a more
+  // specific scope overrides
+  const scopes = { global: {}, collection: {}, environment: {}, data: {}, request: {} };
+  for (const scope of Object.keys(input.scopes)) {
+    Object.assign(scopes[scope], input.scopes[scope]);
+  }
+
+  function format(value) {
+    if (typeof value === 'string') return value;
+    try {
+      return JSON.stringify(value);
+    } catch (e) {
+      return String(value);
+    }
+  }
+
+  function log(level) {
+    return function () {
+      logs.push({ level: level, message: Array.prototype.map.call(arguments, format).join(' ') });
+    };
+  }
+
+  global.console = {
+    log: log('log'),
+    info: log('info'),
+    warn: log('warn'),
+    error: log('error'),
+    debug: log('debug'),
+  };
+
+  function variableScope(scope) {
+    return {
+      get: function (key) {
+        return scopes[scope][key];
+      },
+      has: function (key) {
+        return Object.prototype.hasOwnProperty.call(scopes[scope], key);
+      },
+      set: function (key, value) {
+        const text = value === undefined || value === null ? '' : format(value);
+        scopes[scope][key] = text;
+        updates.push({ scope: scope, key: String(key), value: text });
+      },
+      unset: function (key) {
+        delete scopes[scope][key];
+        updates.push({ scope: scope, key: String(key), value: null });
+      },
+      toObject: function () {
+        return Object.assign({}, scopes[scope]);
+      },
+    };
+  }
+
+  // pm.variables reads across scopes, most specific first, and sets request-local values
+  const variables = variableScope('request');
+  variables.get = function (key) {
+    for (const scope of ['request', 'data', 'environment', 'collection', 'global']) {
+      if (Object.prototype.hasOwnProperty.call(scopes[scope], key)) return scopes[scope][key];
+    }
+    return undefined;
+  };
+  variables.has = function (key) {
+    return variables.get(key) !== undefined;
+  };
+  variables.replaceIn = function (text) {
+    return String(text).replace(/\{\{\s*([^{}|]+?)\s*\}\}/g, function (match, key) {
+      const value = variables.get(key);
+      return value === undefined ? match : value;
+    });
+  };
+
+  // The collection runner's current data row; read-only
+  const iterationData = {
+    get: function (key) {
+      return scopes.data[key];
+    },
+    has: function (key) {
+      return Object.prototype.hasOwnProperty.call(scopes.data, key);
+    },
+    toObject: function () {
+      return Object.assign({}, scopes.data);
+    },
+    toJSON: function () {
+      return Object.assign({}, scopes.data);
+    },
+  };
+
+  function headerList(headers) {
+    return {
+      all: function () {
+        return headers.slice();
+      },
+      get: function (key) {
+        const lower = String(key).toLowerCase();
+        const found = headers.find(function (h) {
+          return h.key.toLowerCase() === lower && h.enabled !== false;
+        });
+        return found ? found.value : undefined;
+      },
+      has: function (key) {
+        return this.get(key) !== undefined;
+      },
+      add: function (header) {
+        headers.push({ key: String(header.key), value: format(header.value), enabled: true });
+      },
+      upsert: function (header) {
+        const lower = String(header.key).toLowerCase();
+        const found = headers.find(function (h) {
+          return h.key.toLowerCase() === lower;
+        });
+        if (found) {
+          found.value = format(header.value);
+          found.enabled = true;
+        } else {
+          this.add(header);
+        }
+      },
+      remove: function (key) {
+        const lower = String(key).toLowerCase();
+        for (let i = headers.length - 1; i >= 0; i--) {
+          if (headers[i].key.toLowerCase() === lower) headers.splice(i, 1);
+        }
+      },
+      toObject: function () {
+        const object = {};
+        for (const h of headers) {
+          if (h.enabled !== false) object[h.key] = h.value;
+        }
+        return object;
+      },
+    };
+  }
+
+  const requestHeaders = input.request.headers.map(function (h) {
+    return { key: h.key, value: h.value, enabled: h.enabled };
+  });
+  const request = {
+    method: input.request.method,
+    url: input.request.url,
+    headers: headerList(requestHeaders),
+    body: { raw: input.request.body },
+  };
+
+  // Chai-style assertions covering the common Postman usage
+  function AssertionError(message) {
+    const error = new Error(message);
+    error.name = 'AssertionError';
+    return error;
+  }
+
+  function deepEqual(a, b) {
+    return JSON.stringify(a) === JSON.stringify(b);
+  }
+
+  function typeOf(value) {
+    if (value === null) return 'null';
+    if (Array.isArray(value)) return 'array';
+    return typeof value;
+  }
+
+  function expect(actual) {
+    let negate = false;
+    let deep = false;
+    const assertion = {};
+
+    function check(passed, message) {
+      if (passed === negate) {
+        throw AssertionError((negate ? 'expected not: ' : 'expected: ') + message);
+      }
+      return assertion;
+    }
+
+    const chains = ['to', 'be', 'been', 'is', 'that', 'which', 'and', 'has', 'have', 'with', 'at', 'of', 'same', 'does'];
+    for (const word of chains) {
+      Object.defineProperty(assertion, word, { get: function () { return assertion; } });
+    }
+    Object.defineProperty(assertion, 'not', { get: function () { negate = !negate; return assertion; } });
+    Object.defineProperty(assertion, 'deep', { get: function () { deep = true; return assertion; } });
+
+    function equal(expected) {
+      const passed = deep ? deepEqual(actual, expected) : actual === expected;
+      return check(passed, format(actual) + ' to equal ' + format(expected));
+    }
+    assertion.equal = assertion.equals = assertion.eq = equal;
+    assertion.eql = function (expected) {
+      return check(deepEqual(actual, expected), format(actual) + ' to deeply equal ' + format(expected));
+    };
+
+    const flags = {
+      ok: function () {
+        if (actual === response && response) {
+          return check(actual.code >= 200 && actual.code < 300, 'status ' + actual.code + ' to be 2xx');
+        }
+        return check(!!actual, format(actual) + ' to be truthy');
+      },
+      true: function () { return check(actual === true, format(actual) + ' to be true'); },
+      false: function () { return check(actual === false, format(actual) + ' to be false'); },
+      null: function () { return check(actual === null, format(actual) + ' to be null'); },
+      undefined: function () { return check(actual === undefined, format(actual) + ' to be undefined'); },
+      empty: function () {
+        const size = typeof actual === 'string' || Array.isArray(actual)
+          ? actual.length
+          : Object.keys(actual || {}).length;
+        return check(size === 0, format(actual) + ' to be empty');
+      },
+    };
+    for (const name of Object.keys(flags)) {
+      Object.defineProperty(assertion, name, { get: flags[name] });
+    }
+
+    assertion.a = assertion.an = function (type) {
+      return check(typeOf(actual) === String(type).toLowerCase(), format(actual) + ' to be a ' + type);
+    };
+    assertion.include = assertion.includes = assertion.contain = assertion.contains = function (item) {
+      let passed;
+      if (typeof actual === 'string') passed = actual.indexOf(item) !== -1;
+      else if (Array.isArray(actual)) passed = actual.some(function (a) { return deepEqual(a, item); });
+      else passed = Object.keys(item || {}).every(function (k) { return deepEqual(actual[k], item[k]); });
+      return check(passed, format(actual) + ' to include ' + format(item));
+    };
+    assertion.property = function (name, value) {
+      const has = actual !== null && actual !== undefined && Object.prototype.hasOwnProperty.call(Object(actual), name);
+      if (arguments.length > 1) {
+        return check(has && deepEqual(actual[name], value), format(actual) + ' to have property ' + name + ' of ' + format(value));
+      }
+      return check(has, format(actual) + ' to have property ' + name);
+    };
+    assertion.lengthOf = function (length) {
+      return check(actual !== null && actual !== undefined && actual.length === length, format(actual) + ' to have length ' + length);
+    };
+    assertion.above = assertion.greaterThan = assertion.gt = function (n) {
+      return check(actual > n, format(actual) + ' to be above ' + n);
+    };
+    assertion.below = assertion.lessThan = assertion.lt = function (n) {
+      return check(actual < n, format(actual) + ' to be below ' + n);
+    };
+    assertion.least = assertion.gte = function (n) {
+      return check(actual >= n, format(actual) + ' to be at least ' + n);
+    };
+    assertion.most = assertion.lte = function (n) {
+      return check(actual <= n, format(actual) + ' to be at most ' + n);
+    };
+    assertion.oneOf = function (list) {
+      return check(list.some(function (item) { return deepEqual(item, actual); }), format(actual) + ' to be one of ' + format(list));
+    };
+    assertion.match = function (pattern) {
+      return check(pattern.test(String(actual)), format(actual) + ' to match ' + pattern);
+    };
+
+    // pm.response.to.have.status(200) and friends
+    assertion.status = function (expected) {
+      if (typeof expected === 'number') {
+        return check(actual.code === expected, 'status ' + actual.code + ' to be ' + expected);
+      }
+      return check(actual.status === expected, 'status "' + actual.status + '" to be "' + expected + '"');
+    };
+    assertion.header = function (name, value) {
+      const found = actual.headers.get(name);
+      if (arguments.length > 1) {
+        return check(found === value, 'header ' + name + ' to be ' + format(value) + ', got ' + format(found));
+      }
+      return check(found !== undefined, 'header ' + name + ' to be present');
+    };
+    assertion.jsonBody = function () {
+      let parsed = true;
+      try { actual.json(); } catch (e) { parsed = false; }
+      return check(parsed, 'response body to be JSON');
+    };
+
+    return assertion;
+  }
+
+  let response;
+  if (input.response) {
+    const r = input.response;
+    response = {
+      code: r.code,
+      status: r.status,
+      responseTime: r.responseTime,
+      responseSize: r.body.length,
+      headers: headerList(r.headers),
+      text: function () { return r.body; },
+      json: function () { return JSON.parse(r.body); },
+    };
+    Object.defineProperty(response, 'to', { get: function () { return expect(response).to; } });
+  }
+
+  function unsupported(name) {
+    return function () {
+      throw new Error(name + ' is not supported in fetchr scripts');
+    };
+  }
+
+  global.pm = {
+    info: { eventName: input.eventName, requestName: input.requestName },
+    request: request,
+    response: response,
+    variables: variables,
+    environment: variableScope('environment'),
+    collectionVariables: variableScope('collection'),
+    globals: variableScope('global'),
+    iterationData: iterationData,
+    expect: expect,
+    test: function (name, fn) {
+      try {
+        fn();
+        tests.push({ name: String(name), passed: true, error: null });
+      } catch (e) {
+        tests.push({ name: String(name), passed: false, error: e && e.message ? e.message : String(e) });
+      }
+    },
+    sendRequest: unsupported('pm.sendRequest'),
+    setNextRequest: unsupported('pm.setNextRequest'),
+  };
+
+  global.__output = function () {
+    return JSON.stringify({
+      request: {
+        method: String(request.method),
+        url: String(request.url),
+        headers: requestHeaders,
+        body: request.body && request.body.raw !== undefined ? String(request.body.raw) : '',
+      },
+      updates: updates,
+      logs: logs,
+      tests: tests,
+    });
+  };
+})(globalThis);
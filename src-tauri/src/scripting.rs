@@ -0,0 +1,639 @@
+use crate::db::{Collection, Database};
+use crate::http_client::{HttpRequest, HttpResponse, KeyValue};
+use crate::secrets::Vault;
+use crate::variables::{persist_updates, VariableContext, VariableUpdate};
+use boa_engine::{Context, JsResult, JsValue, Script, Source};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::future::Future;
+use std::task::{Poll, Waker};
+use std::time::{Duration, Instant};
+
+const PRELUDE: &str = include_str!("script_prelude.js");
+
+// Scripts get no I/O; these bound runaway loops and recursion
+const LOOP_ITERATION_LIMIT: u64 = 10_000_000;
+const RECURSION_LIMIT: usize = 512;
+// Loop limits count per function call, so a loop calling a looping function
+// is caught by the wall-clock limit instead
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(5);
+// VM cycles run between deadline checks
+const DEADLINE_CHECK_CYCLES: u32 = 10_000;
+
+// Postman script APIs the local runtime doesn't provide, written as shown in
+// warnings; `require()`, `tests[]` and `postman.*` match their opening text
+const UNSUPPORTED_APIS: [&str; 13] = [
+    "pm.sendRequest",
+    "pm.setNextRequest",
+    "pm.cookies",
+    "pm.vault",
+    "pm.visualizer",
+    "pm.execution",
+    "pm.require",
+    "require()",
+    "postman.*",
+    "tests[]",
+    "xml2Json",
+    "CryptoJS",
+    "cheerio",
+];
+
+// The unsupported APIs a script refers to, for warnings on import
+pub fn unsupported_apis(script: &str) -> Vec<&'static str> {
+    UNSUPPORTED_APIS
+        .iter()
+        .copied()
+        .filter(|api| script.contains(api.trim_end_matches([')', ']', '*'])))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScriptEvent {
+    PreRequest,
+    Test,
+}
+
+impl ScriptEvent {
+    // Postman's pm.info.eventName
+    fn name(self) -> &'static str {
+        match self {
+            ScriptEvent::PreRequest => "prerequest",
+            ScriptEvent::Test => "test",
+        }
+    }
+
+    fn script<'a>(self, pre_request: &'a str, test: &'a str) -> &'a str {
+        match self {
+            ScriptEvent::PreRequest => pre_request,
+            ScriptEvent::Test => test,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScriptLog {
+    pub level: String,
+    pub message: String,
+    #[serde(default)]
+    pub source: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TestResult {
+    pub name: String,
+    pub passed: bool,
+    pub error: Option<String>,
+    #[serde(default)]
+    pub source: String,
+}
+
+// Logs and test results of every script run for one send
+#[derive(Debug, Default)]
+pub struct ScriptResults {
+    pub logs: Vec<ScriptLog>,
+    pub tests: Vec<TestResult>,
+}
+
+#[derive(Deserialize)]
+struct ScriptOutput {
+    request: ScriptRequest,
+    updates: Vec<VariableUpdate>,
+    logs: Vec<ScriptLog>,
+    tests: Vec<TestResult>,
+}
+
+#[derive(Deserialize)]
+struct ScriptRequest {
+    method: String,
+    url: String,
+    headers: Vec<KeyValue>,
+    body: String,
+}
+
+// Runs the collection, folder and request scripts for an event, outermost
+// first, applying variable changes to `context` and saving them to their scope
+pub struct ScriptRunner<'a> {
+    pub db: &'a Database,
+    pub vault: &'a Vault,
+    pub chain: &'a [Collection],
+    pub environment_id: Option<String>,
+}
+
+impl ScriptRunner<'_> {
+    pub fn run(
+        &self,
+        event: ScriptEvent,
+        request: &mut HttpRequest,
+        context: &mut VariableContext,
+        response: Option<&HttpResponse>,
+        results: &mut ScriptResults,
+    ) -> Result<(), String> {
+        let mut scripts: Vec<(String, String)> = self
+            .chain
+            .iter()
+            .rev()
+            .map(|c| {
+                let code = event.script(&c.pre_request_script, &c.test_script);
+                (c.name.clone(), code.to_string())
+            })
+            .collect();
+        scripts.push((
+            "request".to_string(),
+            event
+                .script(&request.pre_request_script, &request.test_script)
+                .to_string(),
+        ));
+
+        for (source, code) in scripts.iter().filter(|(_, code)| !code.trim().is_empty()) {
+            let (output, mut error) = run_script(event, code, request, context, response)?;
+
+            if event == ScriptEvent::PreRequest {
+                request.method = output.request.method;
+                request.url = output.request.url;
+                request.headers = output.request.headers;
+                request.body = output.request.body;
+            }
+            // After a response, a failed save fails the script instead of
+            // discarding the response
+            if let Err(e) = self.save(&output.updates, context) {
+                if event == ScriptEvent::PreRequest {
+                    return Err(e);
+                }
+                error.get_or_insert(format!("Could not save variables: {}", e));
+            }
+
+            results
+                .logs
+                .extend(output.logs.into_iter().map(|log| ScriptLog {
+                    source: source.clone(),
+                    ..log
+                }));
+            results
+                .tests
+                .extend(output.tests.into_iter().map(|test| TestResult {
+                    source: source.clone(),
+                    ..test
+                }));
+
+            if let Some(error) = error {
+                if event == ScriptEvent::PreRequest {
+                    return Err(format!("Pre-request script error ({}): {}", source, error));
+                }
+                results.logs.push(ScriptLog {
+                    level: "error".to_string(),
+                    message: error.clone(),
+                    source: source.clone(),
+                });
+                results.tests.push(TestResult {
+                    name: "Test script".to_string(),
+                    passed: false,
+                    error: Some(error),
+                    source: source.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    // Applies variable changes to `context` and saves them to their scope
+    pub fn save(
+        &self,
+        updates: &[VariableUpdate],
+        context: &mut VariableContext,
+    ) -> Result<(), String> {
+        for update in updates {
+            context.apply(update);
+        }
+        persist_updates(
+            self.db,
+            self.vault,
+            updates,
+            self.chain.last().map(|c| c.id.as_str()),
+            self.environment_id.as_deref(),
+        )
+    }
+}
+
+// Runs one script in a fresh engine. A script that throws still returns the
+// logs, tests and changes made before the error.
+fn run_script(
+    event: ScriptEvent,
+    code: &str,
+    request: &HttpRequest,
+    context: &VariableContext,
+    response: Option<&HttpResponse>,
+) -> Result<(ScriptOutput, Option<String>), String> {
+    let input = json!({
+        "eventName": event.name(),
+        "requestName": request.name,
+        "request": {
+            "method": request.method,
+            "url": request.url,
+            "headers": request.headers,
+            "body": request.body,
+        },
+        "scopes": context.scope_values(),
+        "response": response.map(|r| json!({
+            "code": r.status,
+            "status": r.status_text,
+            "responseTime": r.response_time as u64,
+            "headers": r.headers.iter().map(|(k, v)| json!({"key": k, "value": v})).collect::<Vec<_>>(),
+            "body": r.body,
+        })),
+    });
+
+    let mut engine = Context::default();
+    engine
+        .runtime_limits_mut()
+        .set_loop_iteration_limit(LOOP_ITERATION_LIMIT);
+    engine
+        .runtime_limits_mut()
+        .set_recursion_limit(RECURSION_LIMIT);
+
+    let setup = format!("globalThis.__input = {};\n{}", input, PRELUDE);
+    engine
+        .eval(Source::from_bytes(&setup))
+        .map_err(|e| format!("Failed to start script engine: {}", e))?;
+
+    // A script that runs out of time leaves the engine mid-call, so nothing
+    // it did is kept
+    let Some(result) = eval_until(&mut engine, code, Instant::now() + SCRIPT_TIMEOUT) else {
+        let output = ScriptOutput {
+            request: ScriptRequest {
+                method: request.method.clone(),
+                url: request.url.clone(),
+                headers: request.headers.clone(),
+                body: request.body.clone(),
+            },
+            updates: Vec::new(),
+            logs: Vec::new(),
+            tests: Vec::new(),
+        };
+        let error = format!(
+            "Script did not finish within {} seconds",
+            SCRIPT_TIMEOUT.as_secs()
+        );
+        return Ok((output, Some(error)));
+    };
+    let error = result.err().map(|e| match e.try_native(&mut engine) {
+        Ok(native) => native.to_string(),
+        Err(_) => e.to_string(),
+    });
+
+    let output = engine
+        .eval(Source::from_bytes("__output()"))
+        .map_err(|e| format!("Failed to read script results: {}", e))?;
+    let output = output
+        .as_string()
+        .map(|s| s.to_std_string_escaped())
+        .ok_or_else(|| "Failed to read script results".to_string())?;
+    let output: ScriptOutput = serde_json::from_str(&output).map_err(|e| e.to_string())?;
+
+    Ok((output, error))
+}
+
+// Evaluates `code`, or returns None once `deadline` passes. Evaluation yields
+// every few thousand VM cycles, which is when the deadline is checked.
+fn eval_until(engine: &mut Context, code: &str, deadline: Instant) -> Option<JsResult<JsValue>> {
+    let script = match Script::parse(Source::from_bytes(code), None, engine) {
+        Ok(script) => script,
+        Err(e) => return Some(Err(e)),
+    };
+    let mut evaluation =
+        std::pin::pin!(script.evaluate_async_with_budget(engine, DEADLINE_CHECK_CYCLES));
+    let mut cx = std::task::Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(result) = evaluation.as_mut().poll(&mut cx) {
+            return Some(result);
+        }
+        if Instant::now() >= deadline {
+            return None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variables::{ResolvedVariable, VariableScope};
+
+    fn variable(key: &str, value: &str, scope: VariableScope) -> ResolvedVariable {
+        ResolvedVariable {
+            key: key.to_string(),
+            value: value.to_string(),
+            scope,
+            source_id: None,
+            source_name: None,
+        }
+    }
+
+    fn request() -> HttpRequest {
+        serde_json::from_value(json!({
+            "method": "GET",
+            "url": "https://example.com/users",
+            "headers": [],
+            "body": "",
+            "body_type": "none",
+            "auth_type": "none",
+            "auth_data": {},
+            "form_data": null,
+            "collection_id": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn eval_stops_at_the_deadline() {
+        let mut engine = Context::default();
+        let code = "function spin() { for (let i = 0; i < 1e6; i++) {} }\nfunction run() { for (;;) spin(); }\nrun();";
+        let start = Instant::now();
+        let result = eval_until(&mut engine, code, start + Duration::from_millis(200));
+        assert!(result.is_none());
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn eval_returns_results_and_errors_in_time() {
+        let deadline = Instant::now() + SCRIPT_TIMEOUT;
+        let mut engine = Context::default();
+        let value = eval_until(
+            &mut engine,
+            "let n = 0; for (let i = 0; i < 10; i++) n += i; n",
+            deadline,
+        );
+        assert_eq!(value.unwrap().unwrap().as_number(), Some(45.0));
+        assert!(eval_until(&mut engine, "let = ;", deadline)
+            .unwrap()
+            .is_err());
+        assert!(eval_until(&mut engine, "throw new Error('boom')", deadline)
+            .unwrap()
+            .is_err());
+    }
+    #[test]
+    fn scope_apis_read_their_own_scope() {
+        let mut context = VariableContext::default();
+        context.add(variable(
+            "host",
+            "global.example.com",
+            VariableScope::Global,
+        ));
+        context.add(variable(
+            "host",
+            "collection.example.com",
+            VariableScope::Collection,
+        ));
+        context.add(variable(
+            "host",
+            "env.example.com",
+            VariableScope::Environment,
+        ));
+        context.add(variable("page", "1", VariableScope::Collection));
+
+        let code = "console.log(pm.globals.get('host'), pm.collectionVariables.get('host'), \
+                    pm.environment.get('host'), pm.variables.get('host'));\n\
+                    console.log(JSON.stringify(pm.collectionVariables.toObject()), pm.environment.has('page'));";
+        let (output, error) =
+            run_script(ScriptEvent::PreRequest, code, &request(), &context, None).unwrap();
+        assert_eq!(error, None);
+        assert_eq!(
+            output.logs[0].message,
+            "global.example.com collection.example.com env.example.com env.example.com"
+        );
+        assert_eq!(
+            output.logs[1].message,
+            r#"{"host":"collection.example.com","page":"1"} false"#
+        );
+    }
+    fn collection(id: &str, pre_request_script: &str, test_script: &str) -> Collection {
+        serde_json::from_value(json!({
+            "id": id,
+            "name": id,
+            "parent_id": null,
+            "is_folder": true,
+            "created_at": "",
+            "variables": json!([{"key": "page", "value": "1"}]).to_string(),
+            "pre_request_script": pre_request_script,
+            "test_script": test_script,
+        }))
+        .unwrap()
+    }
+
+    fn response(status: u16, body: &str) -> HttpResponse {
+        serde_json::from_value(json!({
+            "status": status,
+            "status_text": "OK",
+            "headers": {"content-type": "application/json"},
+            "body": body,
+            "response_time": 15,
+            "size": body.len(),
+            "cookies": [],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn pre_request_scripts_change_the_request() {
+        let db = Database::new(":memory:").unwrap();
+        let vault = Vault::default();
+        let chain = [collection(
+            "api",
+            "pm.request.headers.add({key: 'X-Trace', value: 'abc'}); console.log('collection');",
+            "",
+        )];
+        let runner = ScriptRunner {
+            db: &db,
+            vault: &vault,
+            chain: &chain,
+            environment_id: None,
+        };
+        let mut request = request();
+        request.pre_request_script = "pm.request.method = 'POST';\n\
+            pm.request.url = pm.request.url + '/' + pm.variables.get('id');\n\
+            pm.request.body.raw = JSON.stringify({trace: pm.request.headers.get('x-trace')});\n\
+            console.log('request');"
+            .to_string();
+        let mut context = VariableContext::default();
+        context.add(variable("id", "42", VariableScope::Request));
+        let mut results = ScriptResults::default();
+
+        runner
+            .run(
+                ScriptEvent::PreRequest,
+                &mut request,
+                &mut context,
+                None,
+                &mut results,
+            )
+            .unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.url, "https://example.com/users/42");
+        assert_eq!(request.body, r#"{"trace":"abc"}"#);
+        assert_eq!(request.headers[0].key, "X-Trace");
+        // Outermost script first
+        let logs: Vec<(&str, &str)> = results
+            .logs
+            .iter()
+            .map(|l| (l.source.as_str(), l.message.as_str()))
+            .collect();
+        assert_eq!(logs, [("api", "collection"), ("request", "request")]);
+    }
+
+    #[test]
+    fn pm_test_records_passes_and_failures() {
+        let db = Database::new(":memory:").unwrap();
+        let vault = Vault::default();
+        let runner = ScriptRunner {
+            db: &db,
+            vault: &vault,
+            chain: &[],
+            environment_id: None,
+        };
+        let mut request = request();
+        request.test_script = "pm.test('is ok', () => pm.response.to.have.status(200));\n\
+            pm.test('has id 2', () => pm.expect(pm.response.json().id).to.equal(2));"
+            .to_string();
+        let mut results = ScriptResults::default();
+
+        runner
+            .run(
+                ScriptEvent::Test,
+                &mut request,
+                &mut VariableContext::default(),
+                Some(&response(200, r#"{"id": 1}"#)),
+                &mut results,
+            )
+            .unwrap();
+        let tests: Vec<(&str, bool, Option<&str>)> = results
+            .tests
+            .iter()
+            .map(|t| (t.name.as_str(), t.passed, t.error.as_deref()))
+            .collect();
+        assert_eq!(
+            tests,
+            [
+                ("is ok", true, None),
+                ("has id 2", false, Some("expected: 1 to equal 2")),
+            ]
+        );
+    }
+
+    #[test]
+    fn scope_changes_are_applied_and_saved() {
+        let db = Database::new(":memory:").unwrap();
+        let vault = Vault::default();
+        let env = crate::db::Environment {
+            id: "dev".to_string(),
+            name: "dev".to_string(),
+            variables: json!([{"key": "old", "value": "1"}]).to_string(),
+            is_active: true,
+            created_at: String::new(),
+            dotenv_path: None,
+            parent_id: None,
+        };
+        db.save_environment(&env).unwrap();
+        let chain = [collection(
+            "api",
+            "pm.environment.set('token', 'abc'); pm.environment.unset('old');",
+            "",
+        )];
+        db.create_collection(&chain[0]).unwrap();
+        let runner = ScriptRunner {
+            db: &db,
+            vault: &vault,
+            chain: &chain,
+            environment_id: Some("dev".to_string()),
+        };
+        let mut request = request();
+        // Runs after the collection script, so it sees its changes
+        request.pre_request_script =
+            "pm.collectionVariables.set('page', pm.environment.get('token') + '-2');\n\
+            pm.globals.set('seen', pm.environment.has('old'));\n\
+            pm.variables.set('local', 'only here');"
+                .to_string();
+        let mut context =
+            crate::variables::build_context(&db, &vault, &chain, None, &[], &[]).unwrap();
+        let mut results = ScriptResults::default();
+
+        runner
+            .run(
+                ScriptEvent::PreRequest,
+                &mut request,
+                &mut context,
+                None,
+                &mut results,
+            )
+            .unwrap();
+        let values = context.values();
+        assert_eq!(values["token"], "abc");
+        assert_eq!(values["page"], "abc-2");
+        assert_eq!(values["seen"], "false");
+        assert_eq!(values["local"], "only here");
+        assert!(!values.contains_key("old"));
+
+        let chain = db.get_collection_chain("api").unwrap();
+        let saved = crate::variables::build_context(&db, &vault, &chain, None, &[], &[]).unwrap();
+        let saved = saved.values();
+        assert_eq!(saved["token"], "abc");
+        assert_eq!(saved["page"], "abc-2");
+        assert_eq!(saved["seen"], "false");
+        assert!(!saved.contains_key("old"));
+        assert!(!saved.contains_key("local"));
+    }
+
+    #[test]
+    fn thrown_errors_fail_the_script() {
+        let db = Database::new(":memory:").unwrap();
+        let vault = Vault::default();
+        let runner = ScriptRunner {
+            db: &db,
+            vault: &vault,
+            chain: &[],
+            environment_id: None,
+        };
+
+        // Before the send, an error stops it
+        let mut request = request();
+        request.pre_request_script = "console.log('before'); throw new Error('boom');".to_string();
+        let error = runner
+            .run(
+                ScriptEvent::PreRequest,
+                &mut request,
+                &mut VariableContext::default(),
+                None,
+                &mut ScriptResults::default(),
+            )
+            .unwrap_err();
+        assert!(
+            error.starts_with("Pre-request script error (request)"),
+            "{}",
+            error
+        );
+        assert!(error.contains("boom"), "{}", error);
+
+        // After it, the error is a failed test and the earlier results are kept
+        request.test_script =
+            "pm.test('first', () => {});\nundefinedFunction();\npm.test('never', () => {});"
+                .to_string();
+        let mut results = ScriptResults::default();
+        runner
+            .run(
+                ScriptEvent::Test,
+                &mut request,
+                &mut VariableContext::default(),
+                Some(&response(200, "{}")),
+                &mut results,
+            )
+            .unwrap();
+        assert_eq!(results.tests.len(), 2);
+        assert!(results.tests[0].passed);
+        assert_eq!(results.tests[1].name, "Test script");
+        assert!(!results.tests[1].passed);
+        assert!(results.tests[1]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("undefinedFunction"));
+        assert_eq!(results.logs.last().unwrap().level, "error");
+    }
+}
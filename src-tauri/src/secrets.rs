@@ -0,0 +1,354 @@
+use crate::db::Database;
+use crate::http_client::AuthData;
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chacha20poly1305::aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const ENCRYPTED_PREFIX: &str = "enc:v1:";
+pub const MASK: &str = "********";
+
+// auth_data fields that hold credentials
+pub const SECRET_AUTH_FIELDS: [&str; 4] = ["password", "token", "value_field", "secret"];
+
+const SALT_SETTING: &str = "vault_salt";
+const CHECK_SETTING: &str = "vault_check";
+const CHECK_PLAINTEXT: &str = "fetchr-vault";
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VaultStatus {
+    pub configured: bool,
+    pub unlocked: bool,
+}
+
+// Holds the passphrase-derived key for the current session
+#[derive(Default)]
+pub struct Vault {
+    configured: bool,
+    key: Option<[u8; 32]>,
+}
+
+impl Vault {
+    pub fn load(db: &Database) -> Result<Self, String> {
+        let configured = db
+            .get_setting(SALT_SETTING)
+            .map_err(|e| e.to_string())?
+            .is_some();
+        Ok(Vault {
+            configured,
+            key: None,
+        })
+    }
+
+    pub fn status(&self) -> VaultStatus {
+        VaultStatus {
+            configured: self.configured,
+            unlocked: self.key.is_some(),
+        }
+    }
+
+    pub fn setup(&mut self, db: &Database, passphrase: &str) -> Result<(), String> {
+        if self.configured {
+            return Err("A master passphrase is already set".to_string());
+        }
+        if passphrase.is_empty() {
+            return Err("The master passphrase cannot be empty".to_string());
+        }
+
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let key = derive_key(passphrase, &salt)?;
+        let check = encrypt(&key, CHECK_PLAINTEXT)?;
+
+        db.set_setting(SALT_SETTING, &BASE64.encode(salt))
+            .map_err(|e| e.to_string())?;
+        db.set_setting(CHECK_SETTING, &check)
+            .map_err(|e| e.to_string())?;
+
+        self.configured = true;
+        self.key = Some(key);
+        Ok(())
+    }
+
+    pub fn unlock(&mut self, db: &Database, passphrase: &str) -> Result<(), String> {
+        let salt = db
+            .get_setting(SALT_SETTING)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "No master passphrase has been set".to_string())?;
+        let check = db
+            .get_setting(CHECK_SETTING)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Vault settings are incomplete".to_string())?;
+        let salt = BASE64.decode(salt).map_err(|e| e.to_string())?;
+
+        let key = derive_key(passphrase, &salt)?;
+        match decrypt(&key, &check) {
+            Ok(plaintext) if plaintext == CHECK_PLAINTEXT => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => Err("Incorrect master passphrase".to_string()),
+        }
+    }
+
+    pub fn lock(&mut self) {
+        self.key = None;
+    }
+
+    // Encrypts a value for storage. Without a configured vault values are
+    // stored as-is; with a locked vault saving a new secret is refused.
+    pub fn seal(&self, value: &str) -> Result<String, String> {
+        if value.is_empty() || is_encrypted(value) {
+            return Ok(value.to_string());
+        }
+        match &self.key {
+            Some(key) => encrypt(key, value),
+            None if self.configured => Err("Unlock the vault to save secrets".to_string()),
+            None => Ok(value.to_string()),
+        }
+    }
+
+    // Decrypts a stored value, failing if the vault is locked
+    pub fn open(&self, value: &str) -> Result<String, String> {
+        if !is_encrypted(value) {
+            return Ok(value.to_string());
+        }
+        match &self.key {
+            Some(key) => decrypt(key, value),
+            None => Err("The vault is locked; unlock it to use saved secrets".to_string()),
+        }
+    }
+
+    // Decrypts a stored value for display, leaving it sealed while locked
+    pub fn reveal(&self, value: &str) -> Result<String, String> {
+        match &self.key {
+            Some(_) => self.open(value),
+            None => Ok(value.to_string()),
+        }
+    }
+
+    pub fn open_auth(&self, auth: &mut AuthData) -> Result<(), String> {
+        for field in [
+            &mut auth.password,
+            &mut auth.token,
+            &mut auth.value_field,
+            &mut auth.secret,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *field = self.open(field)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+// Applies `f` to the credential fields of an auth_data JSON string
+pub fn map_auth_secrets(
+    auth_data: &str,
+    f: impl Fn(&str) -> Result<String, String>,
+) -> Result<String, String> {
+    let mut data: Value = match serde_json::from_str(auth_data) {
+        Ok(data) => data,
+        Err(_) => return Ok(auth_data.to_string()),
+    };
+
+    if let Some(object) = data.as_object_mut() {
+        for field in SECRET_AUTH_FIELDS {
+            if let Some(Value::String(value)) = object.get_mut(field) {
+                *value = f(value)?;
+            }
+        }
+    }
+
+    Ok(data.to_string())
+}
+
+// Applies `f` to the values of every variable marked secret in a variables JSON string
+pub fn map_variable_secrets(
+    variables: &str,
+    f: impl Fn(&str) -> Result<String, String>,
+) -> Result<String, String> {
+    let mut data: Value = match serde_json::from_str(variables) {
+        Ok(data) => data,
+        Err(_) => return Ok(variables.to_string()),
+    };
+
+    if let Some(array) = data.as_array_mut() {
+        for var in array.iter_mut() {
+            if var["secret"].as_bool() != Some(true) {
+                continue;
+            }
+            for field in ["value", "initial_value"] {
+                if let Some(Value::String(value)) = var.get_mut(field) {
+                    *value = f(value)?;
+                }
+            }
+        }
+    }
+
+    Ok(data.to_string())
+}
+
+// Plaintext values of the variables marked secret
+pub fn secret_values(variables: &str) -> Vec<String> {
+    let data: Vec<Value> = serde_json::from_str(variables).unwrap_or_default();
+    data.iter()
+        .filter(|var| var["secret"].as_bool() == Some(true))
+        .flat_map(|var| [var["value"].as_str(), var["initial_value"].as_str()])
+        .flatten()
+        .filter(|value| !value.is_empty() && !is_encrypted(value))
+        .map(|value| value.to_string())
+        .collect()
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+fn encrypt(key: &[u8; 32], plaintext: &str) -> Result<String, String> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_| "Encryption failed".to_string())?;
+
+    let mut payload = nonce.to_vec();
+    payload.extend_from_slice(&ciphertext);
+    Ok(format!("{}{}", ENCRYPTED_PREFIX, BASE64.encode(payload)))
+}
+
+fn decrypt(key: &[u8; 32], value: &str) -> Result<String, String> {
+    let payload = BASE64
+        .decode(value.trim_start_matches(ENCRYPTED_PREFIX))
+        .map_err(|_| "Encrypted value is corrupt".to_string())?;
+    if payload.len() < NONCE_LEN {
+        return Err("Encrypted value is corrupt".to_string());
+    }
+
+    let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Failed to decrypt value".to_string())?;
+    String::from_utf8(plaintext).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unlocked_vault(db: &Database) -> Vault {
+        let mut vault = Vault::load(db).unwrap();
+        vault.setup(db, "correct horse").unwrap();
+        vault
+    }
+
+    #[test]
+    fn seal_and_open_round_trip() {
+        let db = Database::new(":memory:").unwrap();
+        let vault = unlocked_vault(&db);
+
+        let sealed = vault.seal("s3cret").unwrap();
+        assert!(sealed.starts_with(ENCRYPTED_PREFIX));
+        assert_ne!(
+            sealed,
+            vault.seal("s3cret").unwrap(),
+            "each seal uses a fresh nonce"
+        );
+        assert_eq!(vault.open(&sealed).unwrap(), "s3cret");
+    }
+
+    #[test]
+    fn unlock_checks_the_passphrase() {
+        let db = Database::new(":memory:").unwrap();
+        let sealed = unlocked_vault(&db).seal("s3cret").unwrap();
+
+        let mut vault = Vault::load(&db).unwrap();
+        assert_eq!(
+            vault.unlock(&db, "wrong horse").unwrap_err(),
+            "Incorrect master passphrase"
+        );
+        assert!(vault.open(&sealed).is_err());
+
+        vault.unlock(&db, "correct horse").unwrap();
+        assert_eq!(vault.open(&sealed).unwrap(), "s3cret");
+    }
+
+    #[test]
+    fn wrong_key_fails_to_decrypt() {
+        let sealed = encrypt(&[1; 32], "s3cret").unwrap();
+        assert_eq!(
+            decrypt(&[2; 32], &sealed).unwrap_err(),
+            "Failed to decrypt value"
+        );
+        assert_eq!(
+            decrypt(&[1; 32], "enc:v1:AAAA").unwrap_err(),
+            "Encrypted value is corrupt"
+        );
+    }
+
+    #[test]
+    fn locked_vault_refuses_new_secrets() {
+        let db = Database::new(":memory:").unwrap();
+        let sealed = unlocked_vault(&db).seal("s3cret").unwrap();
+        let vault = Vault::load(&db).unwrap();
+
+        assert_eq!(
+            vault.seal("new").unwrap_err(),
+            "Unlock the vault to save secrets"
+        );
+        // Already sealed values pass through, so unchanged secrets still save
+        assert_eq!(vault.seal(&sealed).unwrap(), sealed);
+        assert_eq!(vault.reveal(&sealed).unwrap(), sealed);
+    }
+
+    #[test]
+    fn unconfigured_vault_stores_plaintext() {
+        let db = Database::new(":memory:").unwrap();
+        let vault = Vault::load(&db).unwrap();
+        assert_eq!(vault.seal("s3cret").unwrap(), "s3cret");
+        assert_eq!(vault.open("s3cret").unwrap(), "s3cret");
+    }
+
+    #[test]
+    fn prefix_marks_encrypted_values() {
+        assert!(is_encrypted("enc:v1:abc"));
+        assert!(!is_encrypted("enc:v2:abc"));
+        assert!(!is_encrypted("plain enc:v1:"));
+    }
+
+    #[test]
+    fn maps_only_secret_variables() {
+        let variables = r#"[{"key":"a","value":"1","secret":true,"initial_value":"0"},{"key":"b","value":"2"}]"#;
+        let mapped =
+            map_variable_secrets(variables, |v| Ok(format!("{}{}", ENCRYPTED_PREFIX, v))).unwrap();
+        let mapped: Vec<Value> = serde_json::from_str(&mapped).unwrap();
+
+        assert_eq!(mapped[0]["value"], "enc:v1:1");
+        assert_eq!(mapped[0]["initial_value"], "enc:v1:0");
+        assert_eq!(mapped[1]["value"], "2");
+        assert_eq!(secret_values(variables), vec!["1", "0"]);
+    }
+
+    #[test]
+    fn maps_only_credential_auth_fields() {
+        let auth = r#"{"username":"me","password":"pw","token":""}"#;
+        let mapped = map_auth_secrets(auth, |v| Ok(v.to_uppercase())).unwrap();
+        let mapped: Value = serde_json::from_str(&mapped).unwrap();
+
+        assert_eq!(mapped["username"], "me");
+        assert_eq!(mapped["password"], "PW");
+    }
+}
@@ -0,0 +1,80 @@
+use crate::http_client::HttpRequest;
+
+// Renders a request as a cURL command line
+pub fn curl_command(request: &HttpRequest) -> String {
+    let mut parts: Vec<String> = vec!["curl".to_string()];
+
+    // Method
+    if request.method != "GET" {
+        parts.push(format!("-X {}", request.method));
+    }
+
+    // Headers
+    let enabled_headers: Vec<_> = request
+        .headers
+        .iter()
+        .filter(|h| h.enabled && !h.key.is_empty())
+        .collect();
+    for header in &enabled_headers {
+        parts.push(format!(
+            "-H {}",
+            quote(&format!("{}: {}", header.key, header.value))
+        ));
+    }
+
+    // Auth
+    let auth = &request.auth_data;
+    match request.auth_type.as_str() {
+        "basic" if auth.username.is_some() => {
+            parts.push(format!(
+                "-u {}",
+                quote(&format!(
+                    "{}:{}",
+                    auth.username.as_deref().unwrap_or(""),
+                    auth.password.as_deref().unwrap_or("")
+                ))
+            ));
+        }
+        "bearer" if auth.token.is_some() => {
+            parts.push(format!(
+                "-H {}",
+                quote(&format!(
+                    "Authorization: Bearer {}",
+                    auth.token.as_deref().unwrap_or("")
+                ))
+            ));
+        }
+        "apikey" if auth.key.is_some() => {
+            parts.push(format!(
+                "-H {}",
+                quote(&format!(
+                    "{}: {}",
+                    auth.key.as_deref().unwrap_or(""),
+                    auth.value_field.as_deref().unwrap_or("")
+                ))
+            ));
+        }
+        _ => {}
+    }
+
+    // Body
+    if !request.body.is_empty() && request.body_type != "none" {
+        parts.push(format!("-d {}", quote(&request.body)));
+
+        let has_content_type = enabled_headers
+            .iter()
+            .any(|h| h.key.eq_ignore_ascii_case("content-type"));
+        if !has_content_type && request.body_type == "json" {
+            parts.push("-H 'Content-Type: application/json'".to_string());
+        }
+    }
+
+    // URL (always last)
+    parts.push(quote(&request.url));
+
+    parts.join(" \\\n  ")
+}
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
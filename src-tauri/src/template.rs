@@ -0,0 +1,488 @@
+use base64::engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD as BASE64_URL};
+use base64::Engine as _;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+
+pub const FUNCTIONS: [&str; 2] = ["env", "concat"];
+
+pub const FILTERS: [&str; 17] = [
+    "base64",
+    "base64url",
+    "base64decode",
+    "urlencode",
+    "urldecode",
+    "sha1",
+    "sha256",
+    "sha512",
+    "hmac_sha256",
+    "upper",
+    "lower",
+    "trim",
+    "json",
+    "default",
+    "replace",
+    "length",
+    "truncate",
+];
+
+// A placeholder expression: `operand | filter | filter(arg, ...)`
+#[derive(Debug)]
+pub struct Expression {
+    pub operand: Operand,
+    pub filters: Vec<Filter>,
+}
+
+#[derive(Debug)]
+pub enum Operand {
+    Literal(String),
+    Variable(String),
+    Call(String, Vec<Arg>),
+}
+
+#[derive(Debug)]
+pub struct Filter {
+    pub name: String,
+    pub args: Vec<Arg>,
+}
+
+#[derive(Debug)]
+pub enum Arg {
+    Literal(String),
+    Variable(String),
+}
+
+// Whether a placeholder uses filters, a function call or a string literal
+// rather than naming a single variable
+pub fn is_expression(text: &str) -> bool {
+    let text = text.trim();
+    split_outside_quotes(text, '|').len() > 1
+        || text.starts_with('"')
+        || text.starts_with('\'')
+        || call_parts(text).is_some()
+}
+
+pub fn parse(text: &str) -> Result<Expression, String> {
+    let mut segments = split_outside_quotes(text, '|').into_iter();
+    let operand = segments.next().unwrap_or_default();
+    let operand = operand.trim();
+
+    let operand = if operand.is_empty() {
+        return Err("expected a variable, string or function before '|'".to_string());
+    } else if operand.starts_with('"') || operand.starts_with('\'') {
+        Operand::Literal(unquote(operand)?)
+    } else if let Some((name, args)) = call_parts(operand) {
+        if !FUNCTIONS.contains(&name) {
+            return Err(format!(
+                "unknown function '{}' (available: {})",
+                name,
+                FUNCTIONS.join(", ")
+            ));
+        }
+        Operand::Call(name.to_string(), parse_args(args)?)
+    } else {
+        Operand::Variable(operand.to_string())
+    };
+
+    let mut filters = Vec::new();
+    for segment in segments {
+        let segment = segment.trim();
+        let (name, args) = match call_parts(segment) {
+            Some((name, args)) => (name, parse_args(args)?),
+            None if is_identifier(segment) => (segment, Vec::new()),
+            None if segment.is_empty() => return Err("empty filter after '|'".to_string()),
+            None => return Err(format!("invalid filter '{}'", segment)),
+        };
+        if !FILTERS.contains(&name) {
+            return Err(format!(
+                "unknown filter '{}' (available: {})",
+                name,
+                FILTERS.join(", ")
+            ));
+        }
+        filters.push(Filter {
+            name: name.to_string(),
+            args,
+        });
+    }
+
+    Ok(Expression { operand, filters })
+}
+
+pub fn call(name: &str, args: &[String]) -> Result<String, String> {
+    match (name, args) {
+        ("env", [key]) => {
+            std::env::var(key).map_err(|_| format!("environment variable '{}' is not set", key))
+        }
+        ("env", [key, fallback]) => Ok(std::env::var(key).unwrap_or_else(|_| fallback.clone())),
+        ("env", _) => Err("env() takes a name and an optional default".to_string()),
+        ("concat", args) => Ok(args.concat()),
+        _ => Err(format!("unknown function '{}'", name)),
+    }
+}
+
+pub fn apply_filter(name: &str, input: &str, args: &[String]) -> Result<String, String> {
+    let expect_args = |count: usize| {
+        if args.len() == count {
+            Ok(())
+        } else {
+            Err(format!(
+                "filter '{}' takes {} argument{}, got {}",
+                name,
+                count,
+                if count == 1 { "" } else { "s" },
+                args.len()
+            ))
+        }
+    };
+
+    match name {
+        "base64" => expect_args(0).map(|_| BASE64.encode(input)),
+        "base64url" => expect_args(0).map(|_| BASE64_URL.encode(input)),
+        "base64decode" => {
+            expect_args(0)?;
+            let bytes = BASE64
+                .decode(input.trim())
+                .or_else(|_| BASE64_URL.decode(input.trim().trim_end_matches('=')))
+                .map_err(|_| "value is not valid base64".to_string())?;
+            String::from_utf8(bytes).map_err(|_| "decoded value is not UTF-8 text".to_string())
+        }
+        "urlencode" => expect_args(0).map(|_| url_encode(input)),
+        "urldecode" => expect_args(0).and_then(|_| url_decode(input)),
+        "sha1" => expect_args(0).map(|_| hex::encode(Sha1::digest(input.as_bytes()))),
+        "sha256" => expect_args(0).map(|_| hex::encode(Sha256::digest(input.as_bytes()))),
+        "sha512" => expect_args(0).map(|_| hex::encode(Sha512::digest(input.as_bytes()))),
+        "hmac_sha256" => {
+            expect_args(1)?;
+            let mut mac =
+                Hmac::<Sha256>::new_from_slice(args[0].as_bytes()).map_err(|e| e.to_string())?;
+            mac.update(input.as_bytes());
+            Ok(hex::encode(mac.finalize().into_bytes()))
+        }
+        "upper" => expect_args(0).map(|_| input.to_uppercase()),
+        "lower" => expect_args(0).map(|_| input.to_lowercase()),
+        "trim" => expect_args(0).map(|_| input.trim().to_string()),
+        // Escapes the value for use inside a JSON string
+        "json" => {
+            expect_args(0)?;
+            let quoted = serde_json::to_string(input).map_err(|e| e.to_string())?;
+            Ok(quoted[1..quoted.len() - 1].to_string())
+        }
+        "default" => expect_args(1).map(|_| {
+            if input.is_empty() {
+                args[0].clone()
+            } else {
+                input.to_string()
+            }
+        }),
+        "replace" => expect_args(2).map(|_| input.replace(args[0].as_str(), &args[1])),
+        "length" => expect_args(0).map(|_| input.chars().count().to_string()),
+        "truncate" => {
+            expect_args(1)?;
+            let max: usize = args[0]
+                .parse()
+                .map_err(|_| format!("'{}' is not a valid length", args[0]))?;
+            Ok(input.chars().take(max).collect())
+        }
+        _ => Err(format!("unknown filter '{}'", name)),
+    }
+}
+
+// `name(args)` with an identifier name; returns the name and the raw argument text
+fn call_parts(text: &str) -> Option<(&str, &str)> {
+    let open = text.find('(')?;
+    let name = text[..open].trim();
+    let args = text[open + 1..].strip_suffix(')')?;
+    is_identifier(name).then_some((name, args))
+}
+
+fn parse_args(text: &str) -> Result<Vec<Arg>, String> {
+    if text.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    split_outside_quotes(text, ',')
+        .into_iter()
+        .map(|arg| {
+            let arg = arg.trim();
+            if arg.starts_with('"') || arg.starts_with('\'') {
+                unquote(arg).map(Arg::Literal)
+            } else if !arg.is_empty() && arg.parse::<f64>().is_ok() {
+                Ok(Arg::Literal(arg.to_string()))
+            } else if is_variable_name(arg) {
+                Ok(Arg::Variable(arg.to_string()))
+            } else if arg.is_empty() {
+                Err("empty argument".to_string())
+            } else {
+                Err(format!("invalid argument '{}'", arg))
+            }
+        })
+        .collect()
+}
+
+fn unquote(text: &str) -> Result<String, String> {
+    let mut chars = text.chars();
+    let quote = chars.next().unwrap_or('"');
+    let mut result = String::new();
+    let mut escaped = false;
+
+    for (i, c) in chars.enumerate() {
+        if escaped {
+            result.push(match c {
+                'n' => '\n',
+                't' => '\t',
+                other => other,
+            });
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            // The closing quote must end the literal
+            return if i + 2 == text.chars().count() {
+                Ok(result)
+            } else {
+                Err(format!("unexpected text after string {}", text))
+            };
+        } else {
+            result.push(c);
+        }
+    }
+    Err(format!("unterminated string {}", text))
+}
+
+fn split_outside_quotes(text: &str, separator: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+
+    for c in text.chars() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == separator => {
+                parts.push(std::mem::take(&mut current));
+                continue;
+            }
+            None => {}
+        }
+        current.push(c);
+    }
+    parts.push(current);
+    parts
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn is_variable_name(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '$'))
+}
+
+// Percent-encodes everything except RFC 3986 unreserved characters
+fn url_encode(input: &str) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn url_decode(input: &str) -> Result<String, String> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = input
+                    .get(i + 1..i + 3)
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    .ok_or_else(|| format!("invalid percent-encoding at {}", i))?;
+                decoded.push(hex);
+                i += 3;
+            }
+            b'+' => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| "decoded value is not UTF-8 text".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpolation::{interpolate_with_issues, IssueKind};
+    use std::collections::HashMap;
+
+    fn filter(name: &str, input: &str, args: &[&str]) -> Result<String, String> {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        apply_filter(name, input, &args)
+    }
+
+    #[test]
+    fn recognises_expressions() {
+        assert!(is_expression("token | base64"));
+        assert!(is_expression("'literal'"));
+        assert!(is_expression("concat(a, b)"));
+        assert!(!is_expression("user.id"));
+        assert!(!is_expression("$randomInt"));
+    }
+
+    #[test]
+    fn parses_operands_and_filters() {
+        let expression = parse(r#"name | replace(" ", "_") | truncate(8) | upper"#).unwrap();
+        assert!(matches!(&expression.operand, Operand::Variable(name) if name == "name"));
+        let names: Vec<&str> = expression.filters.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["replace", "truncate", "upper"]);
+        assert!(
+            matches!(&expression.filters[0].args[..], [Arg::Literal(a), Arg::Literal(b)] if a == " " && b == "_")
+        );
+        assert!(matches!(&expression.filters[1].args[..], [Arg::Literal(n)] if n == "8"));
+
+        let expression = parse(r#"concat("Bearer ", token)"#).unwrap();
+        assert!(matches!(
+            &expression.operand,
+            Operand::Call(name, args) if name == "concat"
+                && matches!(&args[..], [Arg::Literal(a), Arg::Variable(b)] if a == "Bearer " && b == "token")
+        ));
+
+        let expression = parse(r#"'a | b\'s' | length"#).unwrap();
+        assert!(matches!(&expression.operand, Operand::Literal(text) if text == "a | b's"));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        assert!(parse("token | nope")
+            .unwrap_err()
+            .starts_with("unknown filter 'nope'"));
+        assert!(parse("nope(1)")
+            .unwrap_err()
+            .starts_with("unknown function 'nope'"));
+        assert_eq!(
+            parse("| upper").unwrap_err(),
+            "expected a variable, string or function before '|'"
+        );
+        assert_eq!(parse("token |").unwrap_err(), "empty filter after '|'");
+        assert_eq!(
+            parse("'open | upper").unwrap_err(),
+            "unterminated string 'open | upper"
+        );
+        assert_eq!(
+            parse("token | default(a b)").unwrap_err(),
+            "invalid argument 'a b'"
+        );
+    }
+
+    #[test]
+    fn encoding_filters() {
+        assert_eq!(filter("base64", "user:pass", &[]).unwrap(), "dXNlcjpwYXNz");
+        assert_eq!(filter("base64url", "??>", &[]).unwrap(), "Pz8-");
+        assert_eq!(
+            filter("base64decode", "dXNlcjpwYXNz", &[]).unwrap(),
+            "user:pass"
+        );
+        assert_eq!(filter("base64decode", "Pz8-", &[]).unwrap(), "??>");
+        assert_eq!(
+            filter("urlencode", "a b&c/é~", &[]).unwrap(),
+            "a%20b%26c%2F%C3%A9~"
+        );
+        assert_eq!(
+            filter("urldecode", "a+b%26c%2F%C3%A9", &[]).unwrap(),
+            "a b&c/é"
+        );
+        assert!(filter("urldecode", "100%", &[]).is_err());
+        assert_eq!(
+            filter("json", "say \"hi\"\n", &[]).unwrap(),
+            r#"say \"hi\"\n"#
+        );
+    }
+
+    #[test]
+    fn hash_filters() {
+        assert_eq!(
+            filter("sha1", "abc", &[]).unwrap(),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            filter("sha256", "abc", &[]).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        // RFC 4231 test case 2
+        assert_eq!(
+            filter("hmac_sha256", "what do ya want for nothing?", &["Jefe"]).unwrap(),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn text_filters() {
+        assert_eq!(filter("upper", "abc", &[]).unwrap(), "ABC");
+        assert_eq!(filter("trim", "  a  ", &[]).unwrap(), "a");
+        assert_eq!(filter("default", "", &["x"]).unwrap(), "x");
+        assert_eq!(filter("default", "set", &["x"]).unwrap(), "set");
+        assert_eq!(filter("replace", "a-b-c", &["-", "+"]).unwrap(), "a+b+c");
+        assert_eq!(filter("length", "héllo", &[]).unwrap(), "5");
+        assert_eq!(filter("truncate", "héllo", &["2"]).unwrap(), "hé");
+        assert_eq!(
+            filter("truncate", "abc", &["two"]).unwrap_err(),
+            "'two' is not a valid length"
+        );
+        assert_eq!(
+            filter("replace", "abc", &["a"]).unwrap_err(),
+            "filter 'replace' takes 2 arguments, got 1"
+        );
+    }
+
+    #[test]
+    fn functions() {
+        let args = ["a".to_string(), "b".to_string()];
+        assert_eq!(call("concat", &args).unwrap(), "ab");
+        let missing = "FETCHR_TEST_SURELY_UNSET".to_string();
+        assert!(call("env", std::slice::from_ref(&missing)).is_err());
+        assert_eq!(
+            call("env", &[missing, "fallback".to_string()]).unwrap(),
+            "fallback"
+        );
+    }
+
+    #[test]
+    fn placeholders_evaluate_expressions() {
+        let variables: HashMap<String, String> = [("user", "alice"), ("password", "s3cret")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let mut issues = Vec::new();
+        let result = interpolate_with_issues(
+            r#"{{ concat(user, ":", password) | base64 }} {{ user | upper }} {{ missing | default("guest") }}"#,
+            &variables,
+            "headers[Authorization]",
+            &mut issues,
+        );
+        assert_eq!(result, "YWxpY2U6czNjcmV0 ALICE guest");
+        assert!(issues.is_empty());
+
+        let result = interpolate_with_issues("{{ user | nope }}", &variables, "url", &mut issues);
+        assert_eq!(result, "{{ user | nope }}");
+        assert_eq!(issues[0].kind, IssueKind::Invalid);
+    }
+}
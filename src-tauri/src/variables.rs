@@ -0,0 +1,790 @@
+use crate::db::{Collection, Database, Environment};
+use crate::http_client::KeyValue;
+use crate::secrets::{is_encrypted, map_variable_secrets, secret_values, Vault};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+const SCHEMA_SETTING: &str = "variables_schema";
+const SCHEMA_VERSION: &str = "2";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableType {
+    #[default]
+    String,
+    Number,
+    Bool,
+    Json,
+}
+
+impl VariableType {
+    fn check(self, value: &str) -> Result<(), String> {
+        let valid = match self {
+            VariableType::String => true,
+            VariableType::Number => value.trim().parse::<f64>().is_ok_and(|n| n.is_finite()),
+            VariableType::Bool => matches!(value.trim(), "true" | "false"),
+            VariableType::Json => serde_json::from_str::<serde_json::Value>(value).is_ok(),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("'{}' is not a valid {}", value, self.name()))
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            VariableType::String => "string",
+            VariableType::Number => "number",
+            VariableType::Bool => "boolean",
+            VariableType::Json => "JSON value",
+        }
+    }
+}
+
+// A global, collection or environment variable as stored in the variables JSON.
+// `value` is the current value used when sending; `initial_value` is the
+// shared default, used when no current value is set.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Variable {
+    pub key: String,
+    #[serde(default)]
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initial_value: Option<String>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub secret: bool,
+    #[serde(default, rename = "type")]
+    pub kind: VariableType,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl Variable {
+    pub fn effective_value(&self) -> &str {
+        match self.initial_value.as_deref() {
+            Some(initial) if self.value.is_empty() => initial,
+            _ => &self.value,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// Ordered from lowest to highest precedence
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableScope {
+    Global,
+    Collection,
+    Environment,
+    // A row of the collection runner's data file
+    Data,
+    Request,
+}
+
+// A variable in effect for a request, and the scope that provided it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ResolvedVariable {
+    pub key: String,
+    pub value: String,
+    pub scope: VariableScope,
+    pub source_id: Option<String>,
+    pub source_name: Option<String>,
+}
+
+// A variable set or (with no value) unset by a script
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VariableUpdate {
+    pub scope: VariableScope,
+    pub key: String,
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct VariableContext {
+    // The value in effect for each key, from the highest scope defining it
+    variables: HashMap<String, ResolvedVariable>,
+    // Each scope's own variables, including those a higher scope overrides
+    scopes: BTreeMap<VariableScope, HashMap<String, ResolvedVariable>>,
+    // Values of the variables marked secret in every scope loaded, including
+    // overridden ones, for redaction
+    secrets: Vec<String>,
+}
+
+impl VariableContext {
+    // Within a scope later additions override earlier ones
+    pub fn add(&mut self, variable: ResolvedVariable) {
+        let key = variable.key.clone();
+        self.scopes
+            .entry(variable.scope)
+            .or_default()
+            .insert(key.clone(), variable);
+        self.resolve(&key);
+    }
+
+    // Takes the value from the highest scope that still defines `key`
+    fn resolve(&mut self, key: &str) {
+        match self.scopes.values().rev().find_map(|scope| scope.get(key)) {
+            Some(variable) => {
+                self.variables.insert(key.to_string(), variable.clone());
+            }
+            None => {
+                self.variables.remove(key);
+            }
+        }
+    }
+
+    pub fn values(&self) -> HashMap<String, String> {
+        self.variables
+            .iter()
+            .map(|(k, v)| (k.clone(), v.value.clone()))
+            .collect()
+    }
+
+    pub fn apply(&mut self, update: &VariableUpdate) {
+        match &update.value {
+            Some(value) => self.add(ResolvedVariable {
+                key: update.key.clone(),
+                value: value.clone(),
+                scope: update.scope,
+                source_id: None,
+                source_name: None,
+            }),
+            None => {
+                if let Some(scope) = self.scopes.get_mut(&update.scope) {
+                    scope.remove(&update.key);
+                }
+                self.resolve(&update.key);
+            }
+        }
+    }
+
+    // Each scope's own values by key, for the per-scope script APIs
+    pub fn scope_values(&self) -> BTreeMap<VariableScope, HashMap<String, String>> {
+        self.scopes
+            .iter()
+            .map(|(scope, variables)| {
+                let values = variables
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.value.clone()))
+                    .collect();
+                (*scope, values)
+            })
+            .collect()
+    }
+
+    pub fn secrets(&self) -> &[String] {
+        &self.secrets
+    }
+
+    pub fn explain(&self) -> Vec<ResolvedVariable> {
+        let mut all: Vec<ResolvedVariable> = self.variables.values().cloned().collect();
+        all.sort_by(|a, b| a.key.cmp(&b.key));
+        all
+    }
+
+    fn add_environment(&mut self, env: &Environment, vault: &Vault) -> Result<(), String> {
+        self.add_json(
+            &env.variables,
+            vault,
+            VariableScope::Environment,
+            Some(&env.id),
+            Some(&env.name),
+        )?;
+
+        // Values in a linked .env file override those stored in fetchr.db
+        if let Some(path) = env.dotenv_path.as_deref().filter(|p| !p.trim().is_empty()) {
+            let source_name = format!("{} ({})", env.name, path);
+            for (key, value) in read_dotenv(path)? {
+                self.add(ResolvedVariable {
+                    key,
+                    value,
+                    scope: VariableScope::Environment,
+                    source_id: Some(env.id.clone()),
+                    source_name: Some(source_name.clone()),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn add_json(
+        &mut self,
+        variables: &str,
+        vault: &Vault,
+        scope: VariableScope,
+        source_id: Option<&str>,
+        source_name: Option<&str>,
+    ) -> Result<(), String> {
+        let variables = map_variable_secrets(variables, |v| vault.reveal(v))?;
+        self.secrets.extend(secret_values(&variables));
+        let variables: Vec<Variable> = serde_json::from_str(&variables).unwrap_or_default();
+
+        for var in variables.iter().filter(|v| v.enabled && !v.key.is_empty()) {
+            // Secrets stay out while the vault is locked
+            let value = var.effective_value();
+            if !is_encrypted(value) {
+                self.add(ResolvedVariable {
+                    key: var.key.clone(),
+                    value: value.to_string(),
+                    scope,
+                    source_id: source_id.map(|s| s.to_string()),
+                    source_name: source_name.map(|s| s.to_string()),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Builds the variables in effect for a request:
+// request-local > iteration data > environments (last listed first) >
+// collection (nearest folder first) > global
+pub fn build_context(
+    db: &Database,
+    vault: &Vault,
+    chain: &[Collection],
+    environments: Option<&[String]>,
+    data: &[KeyValue],
+    local: &[KeyValue],
+) -> Result<VariableContext, String> {
+    let mut context = VariableContext::default();
+
+    let globals = db.get_global_variables().map_err(|e| e.to_string())?;
+    context.add_json(&globals, vault, VariableScope::Global, None, None)?;
+
+    for collection in chain.iter().rev() {
+        context.add_json(
+            &collection.variables,
+            vault,
+            VariableScope::Collection,
+            Some(&collection.id),
+            Some(&collection.name),
+        )?;
+    }
+
+    for env_id in environment_ids(db, environments)? {
+        // Parent environments first, so the environment itself overrides them
+        let chain = db
+            .get_environment_chain(&env_id)
+            .map_err(|e| e.to_string())?;
+        if chain.is_empty() {
+            return Err(format!("Environment {} not found", env_id));
+        }
+        for env in chain.iter().rev() {
+            context.add_environment(env, vault)?;
+        }
+    }
+
+    let scopes = [(data, VariableScope::Data), (local, VariableScope::Request)];
+    for (variables, scope) in scopes {
+        for var in variables.iter().filter(|v| v.enabled && !v.key.is_empty()) {
+            context.add(ResolvedVariable {
+                key: var.key.clone(),
+                value: var.value.clone(),
+                scope,
+                source_id: None,
+                source_name: None,
+            });
+        }
+    }
+
+    Ok(context)
+}
+
+// The environments a send uses: those it names, or else the active one
+pub fn environment_ids(db: &Database, requested: Option<&[String]>) -> Result<Vec<String>, String> {
+    match requested {
+        Some(ids) => Ok(ids.to_vec()),
+        None => Ok(db
+            .get_active_environment()
+            .map_err(|e| e.to_string())?
+            .map(|env| vec![env.id])
+            .unwrap_or_default()),
+    }
+}
+
+// Reads a dotenv file; it is read again on every send so edits apply immediately
+pub fn read_dotenv(path: &str) -> Result<Vec<(String, String)>, String> {
+    dotenvy::from_path_iter(path.trim())
+        .map_err(|e| format!("Failed to read .env file {}: {}", path, e))?
+        .map(|item| item.map_err(|e| format!("Invalid .env file {}: {}", path, e)))
+        .collect()
+}
+
+// Rejects a parent that does not exist or would make the environment its own ancestor
+pub fn check_environment_parent(db: &Database, env: &Environment) -> Result<(), String> {
+    let Some(parent_id) = env.parent_id.as_deref() else {
+        return Ok(());
+    };
+    if parent_id == env.id {
+        return Err(format!(
+            "Environment '{}' cannot inherit from itself",
+            env.name
+        ));
+    }
+
+    let chain = db
+        .get_environment_chain(parent_id)
+        .map_err(|e| e.to_string())?;
+    if chain.is_empty() {
+        return Err("Parent environment not found".to_string());
+    }
+    if chain.iter().any(|e| e.id == env.id) {
+        let mut path: Vec<&str> = vec![env.name.as_str()];
+        for ancestor in &chain {
+            path.push(&ancestor.name);
+            if ancestor.id == env.id {
+                break;
+            }
+        }
+        return Err(format!(
+            "Environment '{}' cannot inherit from '{}': that would create a cycle ({})",
+            env.name,
+            chain[0].name,
+            path.join(" -> ")
+        ));
+    }
+    Ok(())
+}
+
+// Checks a variables JSON string against the schema and returns it normalized
+pub fn validate_variables(variables: &str) -> Result<String, String> {
+    let mut parsed: Vec<Variable> =
+        serde_json::from_str(variables).map_err(|e| format!("Invalid variables: {}", e))?;
+    let mut seen = std::collections::HashSet::new();
+
+    for (index, var) in parsed.iter_mut().enumerate() {
+        var.key = var.key.trim().to_string();
+        if var.key.is_empty() {
+            return Err(format!("Variable {} has no name", index + 1));
+        }
+        if var.key.contains("{{") || var.key.contains("}}") || var.key.contains('|') {
+            return Err(format!(
+                "Variable name '{}' cannot contain '{{{{', '}}}}' or '|'",
+                var.key
+            ));
+        }
+        if !seen.insert(var.key.clone()) {
+            return Err(format!("Variable '{}' is defined more than once", var.key));
+        }
+
+        // Encrypted values and placeholders are checked once they are resolved
+        for value in [Some(var.value.as_str()), var.initial_value.as_deref()]
+            .into_iter()
+            .flatten()
+            .filter(|v| !v.is_empty() && !is_encrypted(v) && !v.contains("{{"))
+        {
+            var.kind
+                .check(value)
+                .map_err(|e| format!("Variable '{}': {}", var.key, e))?;
+        }
+    }
+
+    serde_json::to_string(&parsed).map_err(|e| e.to_string())
+}
+
+// Brings variables saved as plain {key, value, secret} pairs up to the current
+// schema; rows that cannot be parsed are left untouched
+pub fn migrate_variable_schema(db: &Database) -> Result<(), String> {
+    if db
+        .get_setting(SCHEMA_SETTING)
+        .map_err(|e| e.to_string())?
+        .as_deref()
+        == Some(SCHEMA_VERSION)
+    {
+        return Ok(());
+    }
+
+    let upgrade = |variables: &str| -> Option<String> {
+        let mut parsed: Vec<Variable> = serde_json::from_str(variables).ok()?;
+        for var in parsed.iter_mut() {
+            if var.initial_value.is_none() {
+                var.initial_value = Some(var.value.clone());
+            }
+        }
+        serde_json::to_string(&parsed).ok()
+    };
+
+    for mut env in db.get_all_environments().map_err(|e| e.to_string())? {
+        if let Some(upgraded) = upgrade(&env.variables) {
+            env.variables = upgraded;
+            db.save_environment(&env).map_err(|e| e.to_string())?;
+        }
+    }
+    for mut collection in db.get_all_collections().map_err(|e| e.to_string())? {
+        if let Some(upgraded) = upgrade(&collection.variables) {
+            collection.variables = upgraded;
+            db.update_collection(&collection)
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    let globals = db.get_global_variables().map_err(|e| e.to_string())?;
+    if let Some(upgraded) = upgrade(&globals) {
+        db.save_global_variables(&upgraded)
+            .map_err(|e| e.to_string())?;
+    }
+
+    db.set_setting(SCHEMA_SETTING, SCHEMA_VERSION)
+        .map_err(|e| e.to_string())
+}
+
+// Saves script variable changes to the scope they target. Request-local
+// changes last only for the current send; iteration data is read-only.
+pub fn persist_updates(
+    db: &Database,
+    vault: &Vault,
+    updates: &[VariableUpdate],
+    collection_id: Option<&str>,
+    environment_id: Option<&str>,
+) -> Result<(), String> {
+    for update in updates {
+        let value = update.value.as_deref();
+        match update.scope {
+            VariableScope::Global => {
+                let globals = db.get_global_variables().map_err(|e| e.to_string())?;
+                let globals = set_variable(&globals, vault, &update.key, value)?;
+                db.save_global_variables(&globals)
+                    .map_err(|e| e.to_string())?;
+            }
+            VariableScope::Collection => {
+                let Some(id) = collection_id else { continue };
+                if let Some(mut collection) = db.get_collection(id).map_err(|e| e.to_string())? {
+                    collection.variables =
+                        set_variable(&collection.variables, vault, &update.key, value)?;
+                    db.update_collection(&collection)
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+            VariableScope::Environment => {
+                let Some(id) = environment_id else { continue };
+                if let Some(mut env) = db.get_environment(id).map_err(|e| e.to_string())? {
+                    env.variables = set_variable(&env.variables, vault, &update.key, value)?;
+                    db.save_environment(&env).map_err(|e| e.to_string())?;
+                }
+            }
+            VariableScope::Data | VariableScope::Request => {}
+        }
+    }
+    Ok(())
+}
+
+// Sets the current value of a variable in a stored variables JSON string,
+// adding it if missing, or removes it when `value` is None
+fn set_variable(
+    variables: &str,
+    vault: &Vault,
+    key: &str,
+    value: Option<&str>,
+) -> Result<String, String> {
+    // Malformed JSON is an error rather than a list to overwrite
+    let mut parsed: Vec<Variable> = serde_json::from_str(variables).map_err(|e| e.to_string())?;
+    match value {
+        Some(value) => match parsed.iter_mut().find(|v| v.key == key) {
+            Some(var) => var.value = value.to_string(),
+            None => parsed.push(Variable {
+                key: key.to_string(),
+                value: value.to_string(),
+                initial_value: None,
+                enabled: true,
+                secret: false,
+                kind: VariableType::String,
+                description: String::new(),
+            }),
+        },
+        None => parsed.retain(|v| v.key != key),
+    }
+    let updated = serde_json::to_string(&parsed).map_err(|e| e.to_string())?;
+    map_variable_secrets(&updated, |v| vault.seal(v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn variable(key: &str, value: &str, scope: VariableScope) -> ResolvedVariable {
+        ResolvedVariable {
+            key: key.to_string(),
+            value: value.to_string(),
+            scope,
+            source_id: None,
+            source_name: None,
+        }
+    }
+
+    fn update(scope: VariableScope, key: &str, value: Option<&str>) -> VariableUpdate {
+        VariableUpdate {
+            scope,
+            key: key.to_string(),
+            value: value.map(|v| v.to_string()),
+        }
+    }
+
+    fn environment(id: &str, variables: &str, extra: serde_json::Value) -> Environment {
+        let mut fields = json!({
+            "id": id,
+            "name": id,
+            "variables": variables,
+            "is_active": false,
+            "created_at": "",
+        });
+        fields
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(fields).unwrap()
+    }
+
+    fn resolve(db: &Database, environment_id: &str) -> Result<HashMap<String, String>, String> {
+        let ids = [environment_id.to_string()];
+        build_context(db, &Vault::default(), &[], Some(&ids), &[], &[]).map(|c| c.values().clone())
+    }
+
+    #[test]
+    fn scopes_keep_overridden_values() {
+        let mut context = VariableContext::default();
+        context.add(variable(
+            "host",
+            "collection.example.com",
+            VariableScope::Collection,
+        ));
+        context.add(variable(
+            "host",
+            "env.example.com",
+            VariableScope::Environment,
+        ));
+
+        assert_eq!(context.values()["host"], "env.example.com");
+        let scopes = context.scope_values();
+        assert_eq!(
+            scopes[&VariableScope::Collection]["host"],
+            "collection.example.com"
+        );
+        assert_eq!(
+            scopes[&VariableScope::Environment]["host"],
+            "env.example.com"
+        );
+
+        // A lower scope changing its value stays overridden
+        context.apply(&update(
+            VariableScope::Collection,
+            "host",
+            Some("api.example.com"),
+        ));
+        assert_eq!(context.values()["host"], "env.example.com");
+
+        // Unsetting the winning value falls back to the next scope
+        context.apply(&update(VariableScope::Environment, "host", None));
+        assert_eq!(context.values()["host"], "api.example.com");
+        context.apply(&update(VariableScope::Collection, "host", None));
+        assert!(!context.values().contains_key("host"));
+    }
+
+    #[test]
+    fn saving_to_malformed_variables_fails() {
+        let db = Database::new(":memory:").unwrap();
+        let vault = Vault::default();
+        db.save_global_variables("[{\"key\": \"a\"").unwrap();
+        let updates = [update(VariableScope::Global, "token", Some("abc"))];
+
+        assert!(persist_updates(&db, &vault, &updates, None, None).is_err());
+        assert_eq!(db.get_global_variables().unwrap(), "[{\"key\": \"a\"");
+
+        db.save_global_variables(r#"[{"key": "a", "value": "1"}]"#)
+            .unwrap();
+        persist_updates(&db, &vault, &updates, None, None).unwrap();
+        let saved: Vec<Variable> =
+            serde_json::from_str(&db.get_global_variables().unwrap()).unwrap();
+        let saved: Vec<(&str, &str)> = saved
+            .iter()
+            .map(|v| (v.key.as_str(), v.value.as_str()))
+            .collect();
+        assert_eq!(saved, [("a", "1"), ("token", "abc")]);
+    }
+
+    #[test]
+    fn dotenv_files_override_stored_values() {
+        let dir = std::env::temp_dir().join(format!("fetchr-dotenv-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(".env");
+        std::fs::write(&path, "# local secrets\nTOKEN=from-file\nQUOTED=\"a b\"\n").unwrap();
+        let db = Database::new(":memory:").unwrap();
+        let stored =
+            r#"[{"key": "TOKEN", "value": "stored"}, {"key": "HOST", "value": "api.example.com"}]"#;
+        let path = path.to_string_lossy().to_string();
+        db.save_environment(&environment("dev", stored, json!({"dotenv_path": path})))
+            .unwrap();
+
+        let values = resolve(&db, "dev").unwrap();
+        assert_eq!(values["TOKEN"], "from-file");
+        assert_eq!(values["QUOTED"], "a b");
+        assert_eq!(values["HOST"], "api.example.com");
+
+        // Edits apply on the next send
+        std::fs::write(dir.join(".env"), "TOKEN=edited\n").unwrap();
+        assert_eq!(resolve(&db, "dev").unwrap()["TOKEN"], "edited");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        let error = resolve(&db, "dev").unwrap_err();
+        assert!(
+            error.starts_with(&format!("Failed to read .env file {}", path)),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn environments_inherit_from_their_parents() {
+        let db = Database::new(":memory:").unwrap();
+        let base =
+            r#"[{"key": "host", "value": "base.example.com"}, {"key": "user", "value": "admin"}]"#;
+        let staging = r#"[{"key": "host", "value": "staging.example.com"}]"#;
+        let eu = r#"[{"key": "region", "value": "eu"}]"#;
+        db.save_environment(&environment("base", base, json!({})))
+            .unwrap();
+        db.save_environment(&environment(
+            "staging",
+            staging,
+            json!({"parent_id": "base"}),
+        ))
+        .unwrap();
+        db.save_environment(&environment("eu", eu, json!({"parent_id": "staging"})))
+            .unwrap();
+
+        let values = resolve(&db, "eu").unwrap();
+        assert_eq!(values["host"], "staging.example.com");
+        assert_eq!(values["user"], "admin");
+        assert_eq!(values["region"], "eu");
+
+        // Deleting a parent re-parents its children onto the grandparent
+        db.delete_environment("staging").unwrap();
+        assert_eq!(
+            db.get_environment("eu")
+                .unwrap()
+                .unwrap()
+                .parent_id
+                .as_deref(),
+            Some("base")
+        );
+        assert_eq!(resolve(&db, "eu").unwrap()["host"], "base.example.com");
+    }
+
+    #[test]
+    fn rejects_parents_that_create_a_cycle() {
+        let db = Database::new(":memory:").unwrap();
+        db.save_environment(&environment("a", "[]", json!({})))
+            .unwrap();
+        db.save_environment(&environment("b", "[]", json!({"parent_id": "a"})))
+            .unwrap();
+        db.save_environment(&environment("c", "[]", json!({"parent_id": "b"})))
+            .unwrap();
+
+        let cycle = environment("a", "[]", json!({"parent_id": "c"}));
+        assert_eq!(
+            check_environment_parent(&db, &cycle).unwrap_err(),
+            "Environment 'a' cannot inherit from 'c': that would create a cycle (a -> c -> b -> a)"
+        );
+        let itself = environment("a", "[]", json!({"parent_id": "a"}));
+        assert_eq!(
+            check_environment_parent(&db, &itself).unwrap_err(),
+            "Environment 'a' cannot inherit from itself"
+        );
+        let missing = environment("a", "[]", json!({"parent_id": "gone"}));
+        assert_eq!(
+            check_environment_parent(&db, &missing).unwrap_err(),
+            "Parent environment not found"
+        );
+        let valid = environment("d", "[]", json!({"parent_id": "c"}));
+        assert!(check_environment_parent(&db, &valid).is_ok());
+    }
+
+    #[test]
+    fn validates_variable_types() {
+        let normalized = validate_variables(
+            r#"[{"key": " port ", "value": "8080", "type": "number"},
+                {"key": "debug", "value": "true", "initial_value": "false", "type": "bool"},
+                {"key": "filter", "value": "{{default_filter}}", "type": "json"},
+                {"key": "note", "value": ""}]"#,
+        )
+        .unwrap();
+        let parsed: Vec<Variable> = serde_json::from_str(&normalized).unwrap();
+        assert_eq!(parsed[0].key, "port");
+        assert_eq!(parsed[1].kind, VariableType::Bool);
+        assert_eq!(parsed[3].kind, VariableType::String);
+
+        let error = |json: &str| validate_variables(json).unwrap_err();
+        assert_eq!(
+            error(r#"[{"key": "port", "value": "80a", "type": "number"}]"#),
+            "Variable 'port': '80a' is not a valid number"
+        );
+        assert_eq!(
+            error(r#"[{"key": "debug", "value": "true", "initial_value": "yes", "type": "bool"}]"#),
+            "Variable 'debug': 'yes' is not a valid boolean"
+        );
+        assert_eq!(
+            error(r#"[{"key": "body", "value": "{", "type": "json"}]"#),
+            "Variable 'body': '{' is not a valid JSON value"
+        );
+        assert_eq!(
+            error(r#"[{"key": "a"}, {"key": " a"}]"#),
+            "Variable 'a' is defined more than once"
+        );
+        assert_eq!(error(r#"[{"key": " "}]"#), "Variable 1 has no name");
+        assert_eq!(
+            error(r#"[{"key": "a|b"}]"#),
+            "Variable name 'a|b' cannot contain '{{', '}}' or '|'"
+        );
+        assert!(error("{").starts_with("Invalid variables: "));
+    }
+
+    #[test]
+    fn migrates_variables_to_initial_values() {
+        let db = Database::new(":memory:").unwrap();
+        db.save_global_variables(r#"[{"key": "host", "value": "api.example.com"}]"#)
+            .unwrap();
+        db.save_environment(&environment("dev", "not json", json!({})))
+            .unwrap();
+
+        migrate_variable_schema(&db).unwrap();
+        let globals: Vec<Variable> =
+            serde_json::from_str(&db.get_global_variables().unwrap()).unwrap();
+        assert_eq!(globals[0].initial_value.as_deref(), Some("api.example.com"));
+        assert_eq!(
+            db.get_environment("dev").unwrap().unwrap().variables,
+            "not json"
+        );
+        assert_eq!(
+            db.get_setting(SCHEMA_SETTING).unwrap().as_deref(),
+            Some(SCHEMA_VERSION)
+        );
+
+        // Runs once; later saves are left as they are
+        db.save_global_variables(r#"[{"key": "host", "value": ""}]"#)
+            .unwrap();
+        migrate_variable_schema(&db).unwrap();
+        assert_eq!(
+            db.get_global_variables().unwrap(),
+            r#"[{"key": "host", "value": ""}]"#
+        );
+    }
+
+    #[test]
+    fn falls_back_to_initial_values() {
+        let db = Database::new(":memory:").unwrap();
+        let variables = r#"[{"key": "host", "value": "", "initial_value": "shared.example.com"},
+            {"key": "user", "value": "me", "initial_value": "admin"}]"#;
+        db.save_environment(&environment("dev", variables, json!({})))
+            .unwrap();
+
+        let values = resolve(&db, "dev").unwrap();
+        assert_eq!(values["host"], "shared.example.com");
+        assert_eq!(values["user"], "me");
+    }
+}
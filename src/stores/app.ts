@@ -10,6 +10,11 @@ import type {
   HttpResponse,
   TreeNode,
   EnvironmentVariable,
+  EnvironmentLinks,
+  VaultStatus,
+  ResolvedVariable,
+  RunSummary,
+  ReportFormat,
 } from '../types';
 
 export const useAppStore = defineStore('app', () => {
@@ -19,6 +24,8 @@ export const useAppStore = defineStore('app', () => {
   const environments = ref<Environment[]>([]);
   const activeEnvironment = ref<Environment | null>(null);
   const history = ref<History[]>([]);
+  const vaultStatus = ref<VaultStatus>({ configured: false, unlocked: false });
+  const redactionEnabled = ref(true);
 
   // Tab management
   interface RequestTab {
@@ -200,6 +207,20 @@ export const useAppStore = defineStore('app', () => {
     }
   }
 
+  async function updateCollection(id: string, changes: Partial<Collection>) {
+    try {
+      const collection = collections.value.find((c) => c.id === id);
+      if (collection) {
+        const updated: Collection = { ...collection, ...changes };
+        await invoke('update_collection', { collection: updated });
+        await loadCollections();
+      }
+    } catch (error) {
+      console.error('Failed to update collection:', error);
+      throw error;
+    }
+  }
+
   async function deleteCollection(id: string) {
     try {
       await invoke('delete_collection', { id });
@@ -237,10 +258,19 @@ export const useAppStore = defineStore('app', () => {
         auth_data: JSON.stringify(currentRequest.value.auth_data),
         created_at: now,
         updated_at: now,
+        variables: JSON.stringify(currentRequest.value.variables || []),
+        pre_request_script: currentRequest.value.pre_request_script || '',
+        test_script: currentRequest.value.test_script || '',
+        assertions: JSON.stringify(currentRequest.value.assertions || []),
+        extractions: JSON.stringify(currentRequest.value.extractions || []),
+        response_schema: currentRequest.value.response_schema
+          ? JSON.stringify(currentRequest.value.response_schema)
+          : '',
       };
       await invoke('save_request', { request });
       await loadRequestsByCollection(collectionId);
       selectedRequestId.value = request.id;
+      currentRequest.value.collection_id = collectionId;
 
       // Update current tab
       if (openTabs.value[activeTabIndex.value]) {
@@ -277,6 +307,13 @@ export const useAppStore = defineStore('app', () => {
             auth_type: request.auth_type,
             auth_data: JSON.parse(request.auth_data || '{}'),
             form_data: [],
+            collection_id: request.collection_id,
+            variables: JSON.parse(request.variables || '[]'),
+            pre_request_script: request.pre_request_script || '',
+            test_script: request.test_script || '',
+            assertions: JSON.parse(request.assertions || '[]'),
+            extractions: JSON.parse(request.extractions || '[]'),
+            response_schema: request.response_schema ? JSON.parse(request.response_schema) : undefined,
           };
           if (openTabs.value[activeTabIndex.value]) {
             openTabs.value[activeTabIndex.value].name = request.name;
@@ -331,6 +368,13 @@ export const useAppStore = defineStore('app', () => {
         auth_type: request.auth_type,
         auth_data: JSON.parse(request.auth_data || '{}'),
         form_data: [],
+        collection_id: request.collection_id,
+        variables: JSON.parse(request.variables || '[]'),
+        pre_request_script: request.pre_request_script || '',
+        test_script: request.test_script || '',
+        assertions: JSON.parse(request.assertions || '[]'),
+        extractions: JSON.parse(request.extractions || '[]'),
+        response_schema: request.response_schema ? JSON.parse(request.response_schema) : undefined,
       } : {
         method: 'GET',
         url: '',
@@ -388,33 +432,13 @@ export const useAppStore = defineStore('app', () => {
       isLoading.value = true;
       currentResponse.value = null;
 
-      // Interpolate variables in URL
-      let url = currentRequest.value.url;
-      if (activeEnvironment.value) {
-        url = await invoke<string>('interpolate_variables', { text: url });
-      }
-
-      // Interpolate variables in headers
-      const headers = [...currentRequest.value.headers];
-      for (let i = 0; i < headers.length; i++) {
-        if (activeEnvironment.value) {
-          headers[i].value = await invoke<string>('interpolate_variables', {
-            text: headers[i].value,
-          });
-        }
-      }
-
-      // Interpolate variables in body
-      let body = currentRequest.value.body;
-      if (activeEnvironment.value && body) {
-        body = await invoke<string>('interpolate_variables', { text: body });
-      }
-
+      // Variables are resolved across the whole request by the backend
+      const settings = JSON.parse(localStorage.getItem('fetchr_settings') || '{}');
+      const tab = openTabs.value[activeTabIndex.value];
       const requestToSend: HttpRequest = {
         ...currentRequest.value,
-        url,
-        headers,
-        body,
+        name: tab?.requestId ? tab.name : '',
+        strict_variables: settings.strictVariables === true,
       };
 
       const response = await invoke<HttpResponse>('send_http_request', {
@@ -423,6 +447,9 @@ export const useAppStore = defineStore('app', () => {
 
       currentResponse.value = response;
 
+      // Extraction rules and scripts may have saved environment variables
+      await loadEnvironments();
+
       // Add to history
       await addHistory({
         id: crypto.randomUUID(),
@@ -431,7 +458,7 @@ export const useAppStore = defineStore('app', () => {
         status: response.status,
         response_time: response.response_time,
         created_at: new Date().toISOString(),
-      });
+      }, requestToSend.collection_id, requestToSend.environment_ids);
     } catch (error) {
       console.error('Request failed:', error);
       throw error;
@@ -440,6 +467,33 @@ export const useAppStore = defineStore('app', () => {
     }
   }
 
+  // Runs every request under a collection or folder, once per data file row;
+  // progress arrives as `collection-run-progress` events
+  async function runCollection(
+    collectionId: string,
+    environmentIds?: string[],
+    dataFile?: string
+  ): Promise<RunSummary> {
+    try {
+      return await invoke<RunSummary>('run_collection', { collectionId, environmentIds, dataFile });
+    } catch (error) {
+      console.error('Collection run failed:', error);
+      throw error;
+    } finally {
+      // Extraction rules and scripts may have saved environment variables
+      await loadEnvironments();
+    }
+  }
+
+  async function renderRunReport(summary: RunSummary, format: ReportFormat): Promise<string> {
+    try {
+      return await invoke<string>('render_run_report', { summary, format });
+    } catch (error) {
+      console.error('Failed to render run report:', error);
+      throw error;
+    }
+  }
+
   // Environments
   async function loadEnvironments() {
     try {
@@ -455,7 +509,8 @@ export const useAppStore = defineStore('app', () => {
   async function saveEnvironment(
     name: string,
     variables: EnvironmentVariable[],
-    isActive = false
+    isActive = false,
+    links: EnvironmentLinks = {}
   ) {
     try {
       const env: Environment = {
@@ -464,18 +519,22 @@ export const useAppStore = defineStore('app', () => {
         variables: JSON.stringify(variables),
         is_active: isActive,
         created_at: new Date().toISOString(),
+        dotenv_path: links.dotenvPath || undefined,
+        parent_id: links.parentId || undefined,
       };
       await invoke('save_environment', { env });
       await loadEnvironments();
     } catch (error) {
       console.error('Failed to save environment:', error);
+      throw error;
     }
   }
 
   async function updateEnvironment(
     id: string,
     name: string,
-    variables: EnvironmentVariable[]
+    variables: EnvironmentVariable[],
+    links: EnvironmentLinks = {}
   ) {
     try {
       const env = environments.value.find((e) => e.id === id);
@@ -484,12 +543,15 @@ export const useAppStore = defineStore('app', () => {
           ...env,
           name,
           variables: JSON.stringify(variables),
+          dotenv_path: links.dotenvPath || undefined,
+          parent_id: links.parentId || undefined,
         };
         await invoke('save_environment', { env: updated });
         await loadEnvironments();
       }
     } catch (error) {
       console.error('Failed to update environment:', error);
+      throw error;
     }
   }
 
@@ -515,6 +577,31 @@ export const useAppStore = defineStore('app', () => {
     }
   }
 
+  // Global variables
+  async function loadGlobalVariables(): Promise<EnvironmentVariable[]> {
+    try {
+      const variables = await invoke<string>('get_global_variables');
+      return JSON.parse(variables || '[]');
+    } catch (error) {
+      console.error('Failed to load global variables:', error);
+      throw error;
+    }
+  }
+
+  async function saveGlobalVariables(variables: EnvironmentVariable[]) {
+    try {
+      await invoke('save_global_variables', { variables: JSON.stringify(variables) });
+    } catch (error) {
+      console.error('Failed to save global variables:', error);
+      throw error;
+    }
+  }
+
+  // Variables in effect for the current request, with the scope each comes from
+  async function explainVariables(): Promise<ResolvedVariable[]> {
+    return await invoke<ResolvedVariable[]>('explain_variables', { request: currentRequest.value });
+  }
+
   // History
   async function loadHistory(limit = 50) {
     try {
@@ -525,9 +612,14 @@ export const useAppStore = defineStore('app', () => {
     }
   }
 
-  async function addHistory(historyItem: History) {
+  // Secrets of the collection and environments the send used are masked in the saved URL
+  async function addHistory(
+    historyItem: History,
+    collectionId?: string,
+    environmentIds?: string[],
+  ) {
     try {
-      await invoke('add_history', { history: historyItem });
+      await invoke('add_history', { history: historyItem, collectionId, environmentIds });
       await loadHistory();
     } catch (error) {
       console.error('Failed to add history:', error);
@@ -543,61 +635,69 @@ export const useAppStore = defineStore('app', () => {
     }
   }
 
-  // Code Generation
-  function generateCurl(): string {
-    const parts: string[] = ['curl'];
-
-    // Method
-    if (currentRequest.value.method !== 'GET') {
-      parts.push(`-X ${currentRequest.value.method}`);
+  // Vault
+  async function loadVaultStatus() {
+    try {
+      vaultStatus.value = await invoke<VaultStatus>('get_vault_status');
+    } catch (error) {
+      console.error('Failed to load vault status:', error);
     }
+  }
 
-    // Headers
-    const enabledHeaders = currentRequest.value.headers.filter(h => h.enabled && h.key);
-    for (const header of enabledHeaders) {
-      parts.push(`-H '${header.key}: ${header.value}'`);
-    }
+  async function setupVault(passphrase: string) {
+    await invoke('setup_vault', { passphrase });
+    await loadVaultStatus();
+  }
 
-    // Auth
-    if (currentRequest.value.auth_type === 'basic' && currentRequest.value.auth_data.username) {
-      parts.push(`-u '${currentRequest.value.auth_data.username}:${currentRequest.value.auth_data.password || ''}'`);
-    } else if (currentRequest.value.auth_type === 'bearer' && currentRequest.value.auth_data.token) {
-      parts.push(`-H 'Authorization: Bearer ${currentRequest.value.auth_data.token}'`);
-    } else if (currentRequest.value.auth_type === 'apikey' && currentRequest.value.auth_data.key) {
-      parts.push(`-H '${currentRequest.value.auth_data.key}: ${currentRequest.value.auth_data.value_field}'`);
-    }
+  async function unlockVault(passphrase: string) {
+    await invoke('unlock_vault', { passphrase });
+    await loadVaultStatus();
+    await Promise.all([loadCollections(), loadEnvironments()]);
+  }
 
-    // Body
-    if (currentRequest.value.body && currentRequest.value.body_type !== 'none') {
-      const body = currentRequest.value.body.replace(/'/g, "'\\''");
-      parts.push(`-d '${body}'`);
+  async function lockVault() {
+    await invoke('lock_vault');
+    await loadVaultStatus();
+    await Promise.all([loadCollections(), loadEnvironments()]);
+  }
 
-      // Add content-type header if not already present
-      const hasContentType = enabledHeaders.some(h =>
-        h.key.toLowerCase() === 'content-type'
-      );
-      if (!hasContentType && currentRequest.value.body_type === 'json') {
-        parts.push(`-H 'Content-Type: application/json'`);
-      }
+  // Redaction
+  async function loadRedactionEnabled() {
+    try {
+      redactionEnabled.value = await invoke<boolean>('get_redaction_enabled');
+    } catch (error) {
+      console.error('Failed to load redaction setting:', error);
     }
+  }
 
-    // URL (always last)
-    parts.push(`'${currentRequest.value.url}'`);
+  async function setRedactionEnabled(enabled: boolean) {
+    try {
+      await invoke('set_redaction_enabled', { enabled });
+      redactionEnabled.value = enabled;
+    } catch (error) {
+      console.error('Failed to update redaction setting:', error);
+    }
+  }
 
-    return parts.join(' \\\n  ');
+  // Code Generation
+  // Snippets are rendered by the backend so secrets are redacted consistently
+  async function generateCurl(): Promise<string> {
+    return await invoke<string>('generate_curl', { request: currentRequest.value });
   }
 
   async function copyAsCurl(): Promise<void> {
-    const curl = generateCurl();
+    const curl = await generateCurl();
     await navigator.clipboard.writeText(curl);
   }
 
   // Import/Export
-  async function importPostmanCollection(jsonContent: string): Promise<void> {
+  // Returns warnings about imported scripts that use unsupported APIs
+  async function importPostmanCollection(jsonContent: string): Promise<string[]> {
     try {
       const imported = await invoke<any>('import_postman_collection', { jsonContent });
       await invoke('save_imported_collection', { imported });
       await loadCollections();
+      return imported.warnings || [];
     } catch (error) {
       console.error('Failed to import collection:', error);
       throw error;
@@ -616,7 +716,7 @@ export const useAppStore = defineStore('app', () => {
 
   // Initialize
   async function initialize() {
-    await Promise.all([loadCollections(), loadEnvironments(), loadHistory()]);
+    await Promise.all([loadCollections(), loadEnvironments(), loadHistory(), loadVaultStatus(), loadRedactionEnabled()]);
   }
 
   return {
@@ -626,6 +726,8 @@ export const useAppStore = defineStore('app', () => {
     environments,
     activeEnvironment,
     history,
+    vaultStatus,
+    redactionEnabled,
     currentRequest,
     currentResponse,
     selectedRequestId,
@@ -641,6 +743,7 @@ export const useAppStore = defineStore('app', () => {
     // Actions
     loadCollections,
     createCollection,
+    updateCollection,
     deleteCollection,
     loadRequestsByCollection,
     saveCurrentRequest,
@@ -648,14 +751,25 @@ export const useAppStore = defineStore('app', () => {
     deleteRequest,
     resetCurrentRequest,
     sendRequest,
+    runCollection,
+    renderRunReport,
     loadEnvironments,
     saveEnvironment,
     updateEnvironment,
     setActiveEnvironment,
     deleteEnvironment,
+    loadGlobalVariables,
+    saveGlobalVariables,
+    explainVariables,
     loadHistory,
     addHistory,
     clearHistory,
+    loadVaultStatus,
+    setupVault,
+    unlockVault,
+    lockVault,
+    loadRedactionEnabled,
+    setRedactionEnabled,
     generateCurl,
     copyAsCurl,
     importPostmanCollection,
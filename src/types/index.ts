@@ -18,6 +18,16 @@ export interface AuthData {
   token?: string;
   key?: string;
   value_field?: string;
+  secret?: string;
+  key_id?: string;
+  algorithm?: 'sha1' | 'sha256' | 'sha512';
+  preset?: 'custom' | 'hawk';
+  header_name?: string;
+  header_template?: string;
+  signed_components?: string[];
+  signature_encoding?: 'base64' | 'hex';
+  timestamp_header?: string;
+  ext?: string;
 }
 
 export interface HttpRequest {
@@ -29,6 +39,95 @@ export interface HttpRequest {
   auth_type: string;
   auth_data: AuthData;
   form_data?: FormDataField[];
+  collection_id?: string;
+  strict_variables?: boolean;
+  variables?: KeyValue[];
+  environment_ids?: string[]; // defaults to the active environment
+  pre_request_script?: string;
+  test_script?: string;
+  assertions?: Assertion[];
+  extractions?: ExtractionRule[];
+  response_schema?: ResponseSchema;
+  name?: string; // saved request's name, for pm.info.requestName
+}
+
+// The schema a response is validated against: inline JSON, a file, or a
+// named schema on the request's folder or one of its parents
+export type ResponseSchema =
+  | { source: 'inline'; schema: string }
+  | { source: 'file'; path: string }
+  | { source: 'collection'; name: string };
+
+export interface NamedSchema {
+  name: string;
+  schema: string; // JSON Schema text
+}
+
+export interface SchemaViolation {
+  pointer: string; // JSON pointer into the response body
+  message: string;
+  schema_path: string;
+}
+
+export interface SchemaValidation {
+  valid: boolean;
+  violations: SchemaViolation[];
+  error?: string;
+}
+
+export type AssertionSource = 'status' | 'header' | 'json_path' | 'body' | 'response_time';
+
+export type AssertionOperator =
+  | 'equals'
+  | 'not_equals'
+  | 'contains'
+  | 'not_contains'
+  | 'matches'
+  | 'less_than'
+  | 'less_than_or_equal'
+  | 'greater_than'
+  | 'greater_than_or_equal'
+  | 'exists'
+  | 'not_exists'
+  | 'is_type';
+
+export interface Assertion {
+  enabled: boolean;
+  source: AssertionSource;
+  property: string; // header name or JSON path
+  operator: AssertionOperator;
+  value: string;
+}
+
+export interface AssertionResult {
+  name: string;
+  assertion: Assertion;
+  passed: boolean;
+  actual?: string;
+  message?: string;
+}
+
+export interface ExtractionRule {
+  enabled: boolean;
+  source: 'json_path' | 'xpath' | 'regex' | 'header' | 'cookie' | 'status';
+  expression: string; // JSON path, XPath, regex (first group is used), header or cookie name
+  variable: string;
+  scope: VariableScope;
+}
+
+export interface ExtractionResult {
+  variable: string;
+  scope: VariableScope;
+  value?: string;
+  error?: string;
+}
+
+export interface VariableIssue {
+  kind: 'unresolved' | 'malformed' | 'invalid' | 'cycle' | 'depth_exceeded';
+  name: string;
+  location: string;
+  position: number;
+  message?: string;
 }
 
 export interface Cookie {
@@ -46,14 +145,88 @@ export interface HttpResponse {
   response_time: number;
   size: number;
   cookies: Cookie[];
+  resolved_request?: HttpRequest;
+  variable_issues?: VariableIssue[];
+  script_logs?: ScriptLog[];
+  test_results?: TestResult[];
+  assertion_results?: AssertionResult[];
+  extractions?: ExtractionResult[];
+  schema_validation?: SchemaValidation;
 }
 
+export interface ScriptLog {
+  level: 'log' | 'info' | 'warn' | 'error' | 'debug';
+  message: string;
+  source: string; // collection or folder name, or "request"
+}
+
+export interface TestResult {
+  name: string;
+  passed: boolean;
+  error?: string;
+  source: string;
+}
+
+export interface RequestRunResult {
+  iteration: number; // zero-based data file row
+  request_id: string;
+  name: string;
+  folder: string; // e.g. "Users/Admin", empty at the run's root
+  method: string;
+  url: string;
+  status?: number;
+  response_time: number;
+  passed: boolean;
+  error?: string;
+  assertion_results: AssertionResult[];
+  test_results: TestResult[];
+  schema_validation?: SchemaValidation;
+  details?: FailureDetails; // failed requests only
+}
+
+// A failed request as sent and received, with secrets masked
+export interface FailureDetails {
+  url: string;
+  request_headers: KeyValue[];
+  request_body: string;
+  status_text: string;
+  response_headers: KeyValue[];
+  response_body: string;
+}
+
+export type ReportFormat = 'junit' | 'json' | 'html';
+
+export interface RunSummary {
+  collection_id: string;
+  name: string;
+  started_at: string;
+  duration: number;
+  iterations: number;
+  total: number;
+  passed: number;
+  failed: number;
+  results: RequestRunResult[];
+}
+
+// Emitted as `collection-run-progress` while a collection runs
+export type RunEvent =
+  | { event: 'started'; collection_id: string; iterations: number; total: number }
+  | { event: 'request_started'; index: number; iteration: number; name: string; folder: string }
+  | { event: 'request_finished'; index: number; result: RequestRunResult };
+
 export interface Collection {
   id: string;
   name: string;
   parent_id?: string;
   is_folder: boolean;
   created_at: string;
+  auth_type?: string;
+  auth_data?: string; // JSON string
+  headers?: string; // JSON string of default headers
+  variables?: string; // JSON string of collection variables
+  pre_request_script?: string;
+  test_script?: string;
+  schemas?: string; // JSON string of NamedSchema[]
 }
 
 export interface Request {
@@ -69,6 +242,12 @@ export interface Request {
   auth_data: string; // JSON string
   created_at: string;
   updated_at: string;
+  variables?: string; // JSON string of request-local variables
+  pre_request_script?: string;
+  test_script?: string;
+  assertions?: string; // JSON string of Assertion[]
+  extractions?: string; // JSON string of ExtractionRule[]
+  response_schema?: string; // JSON string of ResponseSchema, empty for none
 }
 
 export interface Environment {
@@ -77,11 +256,40 @@ export interface Environment {
   variables: string; // JSON string
   is_active: boolean;
   created_at: string;
+  dotenv_path?: string; // .env file read on every send
+  parent_id?: string; // environment whose variables this one overrides
 }
 
+export interface EnvironmentLinks {
+  dotenvPath?: string;
+  parentId?: string;
+}
+
+export type VariableType = 'string' | 'number' | 'bool' | 'json';
+
 export interface EnvironmentVariable {
+  key: string;
+  value: string; // current value, used when sending
+  initial_value?: string; // shared default, used when no current value is set
+  enabled?: boolean;
+  secret?: boolean;
+  type?: VariableType;
+  description?: string;
+}
+
+export type VariableScope = 'global' | 'collection' | 'environment' | 'data' | 'request';
+
+export interface ResolvedVariable {
   key: string;
   value: string;
+  scope: VariableScope;
+  source_id?: string;
+  source_name?: string;
+}
+
+export interface VaultStatus {
+  configured: boolean;
+  unlocked: boolean;
 }
 
 export interface History {